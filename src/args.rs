@@ -1,8 +1,407 @@
-use clap::Parser;
+use clap::{ArgGroup, Parser, ValueEnum};
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(author="bahdotsh", version, about, long_about = None)]
+#[command(group(ArgGroup::new("name_list").args(["name_only", "name_status"])))]
 pub struct Args {
     #[arg(short, long, default_value = "main")]
     pub branch: String,
+
+    /// Revision to compare against, in place of `--branch`
+    #[arg(value_name = "BASE", conflicts_with = "branch")]
+    pub base: Option<String>,
+
+    /// A revision to compare `BASE` with instead of `HEAD`, or a path to
+    /// restrict the diff to
+    #[arg(value_name = "HEAD|PATH", requires = "base")]
+    pub head_or_path: Option<String>,
+
+    /// Pathspecs after `--` to restrict the diff to, passed to git as given,
+    /// magic such as `:(exclude)*.lock` included
+    #[arg(last = true, value_name = "PATHSPEC")]
+    pub pathspecs: Vec<String>,
+
+    /// Follow the path given as `HEAD|PATH` through renames within the range,
+    /// like `git log --follow`
+    #[arg(long, conflicts_with = "stash")]
+    pub follow: bool,
+
+    /// Paths the diff is restricted to
+    #[arg(skip)]
+    pub paths: Vec<String>,
+
+    /// Run as if giff was started in this directory, like `git -C`
+    #[arg(short = 'C', value_name = "PATH")]
+    pub directory: Option<PathBuf>,
+
+    /// Diff the working tree at this path (e.g. a linked worktree) against
+    /// the base revision
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["directory", "stash", "pick", "last", "pr", "since", "author", "range_diff", "diff_command"]
+    )]
+    pub worktree: Option<PathBuf>,
+
+    /// Diff what is staged in the index against this revision, like
+    /// `git diff --cached <REF>`
+    #[arg(
+        long,
+        value_name = "REF",
+        conflicts_with_all = ["branch", "base", "worktree", "stash", "pick", "last", "pr", "since", "author", "follow", "range_diff", "diff_command"]
+    )]
+    pub staged: Option<String>,
+
+    /// The git executable to run, instead of `git` from `PATH`
+    #[arg(long, value_name = "PATH", env = "GIFF_GIT")]
+    pub git_path: Option<PathBuf>,
+
+    /// Keep running and redraw whenever the diff changes
+    #[arg(short, long)]
+    pub watch: bool,
+
+    /// With `--watch`, exit once the diff hasn't changed for this many
+    /// seconds
+    #[arg(long, value_name = "SECS", requires = "watch")]
+    pub idle_timeout: Option<u64>,
+
+    /// Print the git command being run to stderr
+    #[arg(short, long)]
+    pub verbose: bool,
+
+    /// Only show files with these change kinds, as in `git diff --diff-filter`
+    #[arg(long, value_name = "ACDMRTUXB", value_parser = parse_diff_filter)]
+    pub diff_filter: Option<String>,
+
+    /// Swap the two sides, showing what it would take to go from head back
+    /// to base
+    #[arg(short = 'R', long)]
+    pub reverse: bool,
+
+    /// Ignore changes whose lines are all blank
+    #[arg(long)]
+    pub ignore_blank_lines: bool,
+
+    /// Ignore whitespace when comparing lines, as in `git diff
+    /// --ignore-all-space`. There's no `-w` shorthand, which is `--watch`
+    #[arg(long)]
+    pub ignore_all_space: bool,
+
+    /// Ignore changes whose lines all match this regex, as in `git diff -I`.
+    /// May be given more than once
+    #[arg(short = 'I', long, value_name = "REGEX", value_parser = parse_regex)]
+    pub ignore_matching_lines: Vec<String>,
+
+    /// Show files whose only change is their line endings line by line,
+    /// instead of as a one-line note
+    #[arg(long)]
+    pub raw_line_endings: bool,
+
+    /// Render trailing spaces as `·`, tabs as `→` and mark the end of each line
+    #[arg(short = 'S', long)]
+    pub show_whitespace: bool,
+
+    /// Always run git instead of reusing a diff cached in `.git/giff-cache`
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Show files matched by `.giffignore` instead of hiding them
+    #[arg(long)]
+    pub no_ignore: bool,
+
+    /// Explain the table's colours and symbols below it
+    #[arg(long)]
+    pub legend: bool,
+
+    /// Show commit SHAs in labels, submodule summaries and range-diffs with
+    /// this many hex digits (default: git's own abbreviation length)
+    #[arg(long, value_name = "N", value_parser = parse_abbrev)]
+    pub abbrev: Option<usize>,
+
+    /// Show at most this many lines of each file's changes
+    #[arg(long, value_name = "N")]
+    pub max_lines: Option<usize>,
+
+    /// List the changed files with their status and line counts instead of
+    /// the full diff
+    #[arg(short, long)]
+    pub summary: bool,
+
+    /// Show only the Nth changed file, counting as `--summary` numbers them
+    #[arg(
+        long,
+        value_name = "N",
+        conflicts_with_all = ["quickfix", "name_list", "compact_summary", "check_whitespace", "output", "patch_dir"]
+    )]
+    pub file: Option<NonZeroUsize>,
+
+    /// With `--summary`, add `git diff --stat` style `+`/`-` bars scaled to
+    /// the largest change
+    #[arg(long)]
+    pub stat_bars: bool,
+
+    /// Print a plain `git diff --compact-summary` style list of the changed
+    /// files, noting new, deleted and mode-changed files, instead of the table
+    #[arg(long, conflicts_with_all = ["summary", "watch"])]
+    pub compact_summary: bool,
+
+    /// List whitespace errors in added lines (per `core.whitespace`) and
+    /// exit non-zero if there are any, instead of showing the diff
+    #[arg(long, conflicts_with_all = ["watch", "output"])]
+    pub check_whitespace: bool,
+
+    /// Print only the names of the changed files, one per line
+    #[arg(long, conflicts_with_all = ["summary", "compact_summary", "watch"])]
+    pub name_only: bool,
+
+    /// Print each changed file's status letter and name, separated by a tab
+    #[arg(long, conflicts_with_all = ["summary", "compact_summary", "watch"])]
+    pub name_status: bool,
+
+    /// End each field of `--name-only`/`--name-status` with NUL instead of a
+    /// tab or newline, for `xargs -0`
+    #[arg(short = 'z', requires = "name_list")]
+    pub null_terminated: bool,
+
+    /// Print `file:line: message` entries for where each hunk's changes
+    /// start, for an editor's quickfix list
+    #[arg(long, conflicts_with_all = ["summary", "compact_summary", "name_list", "watch"])]
+    pub quickfix: bool,
+
+    /// Print `file:line: ...` entries like `--quickfix` for each changed line
+    /// that looks like a function, class or similar header, going by the
+    /// file's extension
+    #[arg(
+        long,
+        conflicts_with_all = ["quickfix", "summary", "compact_summary", "name_list", "watch", "file", "output", "check_whitespace", "patch_dir", "stage_hunks"]
+    )]
+    pub symbols: bool,
+
+    /// Show a stash instead of a branch diff: `N` for its working-tree
+    /// changes, `N:index` for what was staged, `N:untracked` for untracked files
+    #[arg(
+        long,
+        value_name = "N[:index|untracked]",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "0",
+        value_parser = parse_stash
+    )]
+    pub stash: Option<StashSelector>,
+
+    /// Diff the commits made since this date (anything `git log --since` accepts)
+    #[arg(long, value_name = "DATE")]
+    pub since: Option<String>,
+
+    /// Diff the commits made by this author (anything `git log --author` accepts)
+    #[arg(long, value_name = "PATTERN")]
+    pub author: Option<String>,
+
+    /// Show only what the latest commit changed (`HEAD~1..HEAD`)
+    #[arg(short = '1', long, conflicts_with_all = ["since", "author", "stash"])]
+    pub last: bool,
+
+    /// Diff HEAD against where it branched off the remote's default branch
+    /// (`origin/HEAD`), as a pull request would be reviewed
+    #[arg(long, visible_alias = "review", conflicts_with_all = ["since", "author", "last", "stash"])]
+    pub pr: bool,
+
+    /// Choose the commits to compare from a list of recent ones
+    #[arg(long, conflicts_with_all = ["since", "author", "last", "pr", "stash"])]
+    pub pick: bool,
+
+    /// The base and head revisions, when resolved from options other than
+    /// `--branch`
+    #[arg(skip)]
+    pub range: Option<(String, String)>,
+
+    /// Compare two versions of a branch built on BASE with `git range-diff`,
+    /// e.g. before and after a force-push
+    #[arg(
+        long,
+        num_args = 3,
+        value_names = ["BASE", "OLD_TIP", "NEW_TIP"],
+        conflicts_with_all = ["stash", "pick", "last", "since", "author", "output", "summary", "watch"]
+    )]
+    pub range_diff: Option<Vec<String>>,
+
+    /// Show each commit of the range separately, under its subject, instead
+    /// of one diff of the whole range
+    #[arg(
+        long,
+        conflicts_with_all = ["stash", "watch", "summary", "compact_summary", "output", "check_whitespace", "range_diff", "name_list", "quickfix", "worktree", "staged", "patch_dir", "file", "stage_hunks", "symbols"]
+    )]
+    pub annotate_commits: bool,
+
+    /// Run this shell command to produce the diff instead of `git diff`;
+    /// `{left}` and `{right}` are replaced with the base and head revisions
+    #[arg(long, value_name = "TEMPLATE", conflicts_with = "stash")]
+    pub diff_command: Option<String>,
+
+    /// Show only added or only removed lines, with a line of context
+    #[arg(long, value_enum, value_name = "LINES")]
+    pub only: Option<LineFilter>,
+
+    /// Number lines from the start of their hunk instead of the file
+    #[arg(long)]
+    pub hunk_line_numbers: bool,
+
+    /// Start each hunk with a separator showing the file, the line it starts
+    /// at and its position among the file's hunks, in the style of `delta`
+    #[arg(long)]
+    pub decorations: bool,
+
+    /// Show only changed lines and the line either side of them
+    #[arg(long)]
+    pub only_changes: bool,
+
+    /// Lay the table out for this many columns instead of the terminal's
+    /// width, e.g. for output that is redirected or pasted into an email
+    #[arg(long, value_name = "COLS")]
+    pub width: Option<u16>,
+
+    /// Wrap lines longer than this many characters, marking continuations
+    /// with `↪`. Written patches are never wrapped
+    #[arg(long, value_name = "COLS")]
+    pub wrap_at: Option<usize>,
+
+    /// Colour blocks of lines that were moved within a file instead of
+    /// showing them as removed and added
+    #[arg(long)]
+    pub color_moved: bool,
+
+    /// Review the `git format-patch` series (`*.patch` or `*.mbox` files) in
+    /// this directory instead of diffing revisions
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with_all = ["watch", "stash", "staged", "worktree", "pick", "last", "pr", "since", "author", "follow", "range_diff", "diff_command", "output", "check_whitespace", "summary", "compact_summary", "quickfix", "name_list"]
+    )]
+    pub patch_dir: Option<PathBuf>,
+
+    /// Go through the working tree's unstaged hunks one at a time, like
+    /// `git add -p`, and stage the ones answered `y`. `s` splits a hunk into its
+    /// separate groups of changes
+    #[arg(
+        long,
+        conflicts_with_all = ["base", "branch", "watch", "stash", "staged", "worktree", "pick", "last", "pr", "since", "author", "follow", "range_diff", "diff_command", "output", "check_whitespace", "summary", "compact_summary", "quickfix", "name_list", "patch_dir", "file"]
+    )]
+    pub stage_hunks: bool,
+
+    /// Write one patch per file into this directory instead of printing the diff
+    #[arg(short, long, value_name = "DIR", conflicts_with = "watch")]
+    pub output: Option<PathBuf>,
+
+    /// Never clear the screen, draw a spinner or emit colours: just print the
+    /// table, for logs and CI
+    #[arg(long, conflicts_with = "watch")]
+    pub no_interactive: bool,
+
+    /// The terminal's background, so colours can be picked to stay legible
+    /// on it. `auto` reads `COLORFGBG` and otherwise assumes a dark one
+    #[arg(long, value_enum, default_value_t = Background::Auto)]
+    pub background: Background,
+
+    /// How to lay out each file's changes
+    #[arg(long, value_enum, default_value_t = View::SideBySide)]
+    pub view: View,
+
+    /// Shorthand for `--view inline`
+    #[arg(long, conflicts_with = "view")]
+    pub inline: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum View {
+    /// Base and head in separate columns
+    SideBySide,
+    /// One column with old and new line numbers
+    Unified,
+    /// A single full-width column, for narrow terminals
+    Inline,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Background {
+    Auto,
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LineFilter {
+    Added,
+    Removed,
+}
+
+/// Which stash entry, and which part of it, to show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StashSelector {
+    pub index: usize,
+    pub part: StashPart,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StashPart {
+    WorkTree,
+    Index,
+    Untracked,
+}
+
+impl StashSelector {
+    /// The stash commit, e.g. `stash@{0}`.
+    pub fn rev(&self) -> String {
+        format!("stash@{{{}}}", self.index)
+    }
+}
+
+fn parse_stash(selector: &str) -> Result<StashSelector, String> {
+    let (index, part) = match selector.split_once(':') {
+        Some((index, part)) => (index, Some(part)),
+        None => (selector, None),
+    };
+    let index = index
+        .parse()
+        .map_err(|_| format!("invalid stash index '{}'", index))?;
+    let part = match part {
+        None => StashPart::WorkTree,
+        Some("index") => StashPart::Index,
+        Some("untracked") => StashPart::Untracked,
+        Some(other) => {
+            return Err(format!(
+                "unknown stash part '{}', expected 'index' or 'untracked'",
+                other
+            ))
+        }
+    };
+    Ok(StashSelector { index, part })
+}
+
+fn parse_diff_filter(filter: &str) -> Result<String, String> {
+    match filter
+        .chars()
+        .find(|c| !"ACDMRTUXB*".contains(c.to_ascii_uppercase()))
+    {
+        Some(c) => Err(format!(
+            "unknown change kind '{}', expected one of ACDMRTUXB",
+            c
+        )),
+        None => Ok(filter.to_string()),
+    }
+}
+
+/// Git abbreviates SHAs to between 4 and 40 hex digits.
+fn parse_abbrev(value: &str) -> Result<usize, String> {
+    match value.parse() {
+        Ok(len @ 4..=40) => Ok(len),
+        _ => Err("expected a number from 4 to 40".to_string()),
+    }
+}
+
+fn parse_regex(pattern: &str) -> Result<String, String> {
+    regex::Regex::new(pattern)
+        .map(|_| pattern.to_string())
+        .map_err(|err| format!("invalid regex: {}", err))
 }
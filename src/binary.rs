@@ -0,0 +1,125 @@
+use crate::giff;
+use crate::parser::FileChange;
+use std::path::Path;
+
+/// Extensions whose headers [`dimensions`] may understand.
+const IMAGE_EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "gif"];
+
+/// Describes a binary file's change from its blobs, e.g.
+/// `1024×768, 45KB → 1280×960, 61KB`. Images get their dimensions when the
+/// format is recognised; anything else just its size.
+pub fn summary(file: &str, change: &FileChange) -> Option<String> {
+    let (old, new) = change.blobs.as_ref()?;
+    let is_image = Path::new(file)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+    let describe = |blob: &str| {
+        if blob.bytes().all(|b| b == b'0') {
+            return "(none)".to_string();
+        }
+        match read_blob(blob) {
+            Some(bytes) => {
+                let size = format_size(bytes.len());
+                match dimensions(&bytes).filter(|_| is_image) {
+                    Some((width, height)) => format!("{}×{}, {}", width, height, size),
+                    None => size,
+                }
+            }
+            None => "(unreadable)".to_string(),
+        }
+    };
+    Some(format!("{} → {}", describe(old), describe(new)))
+}
+
+/// The size of a blob in bytes: zero for the all-zero SHA git gives a side
+/// that doesn't exist, `None` when the blob can't be read.
+pub fn blob_size(blob: &str) -> Option<usize> {
+    if blob.bytes().all(|b| b == b'0') {
+        return Some(0);
+    }
+    read_blob(blob).map(|bytes| bytes.len())
+}
+
+fn read_blob(blob: &str) -> Option<Vec<u8>> {
+    let output = giff::git().args(["cat-file", "blob", blob]).output().ok()?;
+    output.status.success().then_some(output.stdout)
+}
+
+/// Width and height from a PNG, GIF or JPEG header.
+pub fn dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let be16 = |at: usize| {
+        bytes
+            .get(at..at + 2)
+            .map(|b| u32::from(b[0]) << 8 | u32::from(b[1]))
+    };
+    let le16 = |at: usize| {
+        bytes
+            .get(at..at + 2)
+            .map(|b| u32::from(b[1]) << 8 | u32::from(b[0]))
+    };
+    let be32 = |at: usize| Some(be16(at)? << 16 | be16(at + 2)?);
+
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        // The IHDR chunk always comes first.
+        return Some((be32(16)?, be32(20)?));
+    }
+    if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        return Some((le16(6)?, le16(8)?));
+    }
+    if bytes.starts_with(&[0xff, 0xd8]) {
+        // Walk the segments to the start-of-frame, which holds the size.
+        let mut at = 2;
+        while *bytes.get(at)? == 0xff {
+            let marker = *bytes.get(at + 1)?;
+            let is_frame = (0xc0..=0xcf).contains(&marker) && ![0xc4, 0xc8, 0xcc].contains(&marker);
+            if is_frame {
+                return Some((be16(at + 7)?, be16(at + 5)?));
+            }
+            at += 2 + be16(at + 2)? as usize;
+        }
+    }
+    None
+}
+
+fn format_size(bytes: usize) -> String {
+    const KB: usize = 1024;
+    if bytes < KB {
+        format!("{}B", bytes)
+    } else if bytes < KB * KB {
+        format!("{}KB", (bytes + KB / 2) / KB)
+    } else {
+        format!("{:.1}MB", bytes as f64 / (KB * KB) as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn png_size_comes_from_ihdr() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend([0, 0, 4, 0, 0, 0, 3, 0]);
+        assert_eq!(dimensions(&png), Some((1024, 768)));
+    }
+
+    #[test]
+    fn gif_size_is_little_endian() {
+        assert_eq!(dimensions(b"GIF89a\x40\x01\xf0\x00"), Some((320, 240)));
+    }
+
+    #[test]
+    fn jpeg_segments_are_walked_to_the_frame() {
+        let mut jpeg = vec![0xff, 0xd8, 0xff, 0xe0, 0x00, 0x04, 0x00, 0x00];
+        jpeg.extend([0xff, 0xc0, 0x00, 0x11, 0x08, 0x01, 0xe0, 0x02, 0x80]);
+        assert_eq!(dimensions(&jpeg), Some((640, 480)));
+    }
+
+    #[test]
+    fn unknown_or_truncated_headers_have_no_size() {
+        assert_eq!(dimensions(b"plain text"), None);
+        assert_eq!(dimensions(b"\x89PNG\r\n\x1a\n\0\0"), None);
+        assert_eq!(dimensions(&[0xff, 0xd8, 0xff, 0xe0, 0x00]), None);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::args::Args;
+use crate::giff;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+/// How many diffs are kept; storing one more removes the least recently
+/// written.
+const MAX_ENTRIES: usize = 50;
+
+/// Location of the cached diff for the requested range, under `.git/giff-cache/`.
+///
+/// The key is the resolved commit SHA of both ends plus a hash of everything
+/// else that shapes git's output: the git arguments, the directory within the
+/// repository git runs in (which relative pathspecs are read from), the
+/// `diff.*` config and the git program. Moving either ref or changing any of
+/// those misses the cache.
+/// Returns `None` when the refs can't be resolved.
+pub fn cache_path(args: &Args) -> Option<PathBuf> {
+    let (base, head) = giff::revisions(args);
+    let output = giff::git()
+        .args([
+            "rev-parse",
+            "--absolute-git-dir",
+            "--show-prefix",
+            &base,
+            &head,
+        ])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines();
+    let git_dir = lines.next()?;
+    // Empty at the top of the work tree.
+    let prefix = lines.next()?;
+    let base_sha = lines.next()?;
+    let head_sha = lines.next()?;
+
+    // `diff.renames`, `diff.algorithm`, `diff.noprefix` and the like change
+    // the diff without changing the arguments. None set is fine.
+    let config = giff::git_output(&["config", "--get-regexp", r"^diff\."]).unwrap_or_default();
+    let program = giff::git().get_program().to_os_string();
+    let key = [
+        giff::diff_args(args).join("\0"),
+        prefix.to_string(),
+        config,
+        program.to_string_lossy().into_owned(),
+    ];
+    Some(
+        Path::new(git_dir)
+            .join("giff-cache")
+            .join(file_name(base_sha, head_sha, &key)),
+    )
+}
+
+/// The cache file for a range, named after its SHAs and a hash of `key`.
+fn file_name(base_sha: &str, head_sha: &str, key: &[String]) -> String {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    format!("{}-{}-{:016x}.diff", base_sha, head_sha, hasher.finish())
+}
+
+pub fn load(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok()
+}
+
+/// Writes the diff to the cache. Failures are ignored; the cache is only an
+/// optimisation.
+pub fn store(path: &Path, diff_output: &str) {
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+        evict(dir, MAX_ENTRIES - 1);
+    }
+    let _ = fs::write(path, diff_output);
+}
+
+/// Removes the oldest diffs in `dir` until at most `keep` are left.
+fn evict(dir: &Path, keep: usize) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut diffs: Vec<_> = entries
+        .flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "diff"))
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .collect();
+    if diffs.len() <= keep {
+        return;
+    }
+    diffs.sort();
+    for (_, path) in &diffs[..diffs.len() - keep] {
+        let _ = fs::remove_file(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    fn key(config: &str) -> Vec<String> {
+        ["diff\0main..HEAD", "", config, "git"]
+            .map(String::from)
+            .to_vec()
+    }
+
+    #[test]
+    fn same_shas_and_key_hit_the_same_entry() {
+        assert_eq!(
+            file_name("aaa", "bbb", &key("")),
+            file_name("aaa", "bbb", &key(""))
+        );
+    }
+
+    #[test]
+    fn a_moved_ref_misses() {
+        assert_ne!(
+            file_name("aaa", "bbb", &key("")),
+            file_name("aaa", "ccc", &key(""))
+        );
+        assert_ne!(
+            file_name("aaa", "bbb", &key("")),
+            file_name("ddd", "bbb", &key(""))
+        );
+    }
+
+    #[test]
+    fn diff_config_misses() {
+        assert_ne!(
+            file_name("aaa", "bbb", &key("")),
+            file_name("aaa", "bbb", &key("diff.renames false"))
+        );
+    }
+
+    #[test]
+    fn eviction_keeps_the_newest_diffs() {
+        let dir = std::env::temp_dir().join(format!("giff-cache-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let now = SystemTime::now();
+        for age in 0..5 {
+            let file = fs::File::create(dir.join(format!("{}.diff", age))).unwrap();
+            file.set_modified(now - Duration::from_secs(age * 60))
+                .unwrap();
+        }
+        fs::write(dir.join("notes.txt"), "").unwrap();
+
+        evict(&dir, 2);
+        let mut left: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        left.sort();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(left, ["0.diff", "1.diff", "notes.txt"]);
+    }
+}
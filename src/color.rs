@@ -0,0 +1,226 @@
+use crate::args::Background;
+use comfy_table::Color;
+use std::env;
+use std::sync::OnceLock;
+
+static LIGHT_BACKGROUND: OnceLock<bool> = OnceLock::new();
+
+/// Records the terminal background from `--background`. With
+/// [`Background::Auto`] it is taken from `COLORFGBG` (`fg;bg`, as rxvt and
+/// Konsole set it), where background 7 or 15 means a light one; anything
+/// else is assumed dark.
+pub fn set_background(background: Background) {
+    let colorfgbg = env::var("COLORFGBG").ok();
+    let _ = LIGHT_BACKGROUND.set(is_light(background, colorfgbg.as_deref()));
+}
+
+fn is_light(background: Background, colorfgbg: Option<&str>) -> bool {
+    match background {
+        Background::Light => true,
+        Background::Dark => false,
+        Background::Auto => colorfgbg
+            .and_then(|value| value.rsplit(';').next())
+            .is_some_and(|bg| bg == "7" || bg == "15"),
+    }
+}
+
+pub fn is_light_background() -> bool {
+    LIGHT_BACKGROUND.get().copied().unwrap_or(false)
+}
+
+/// How many colours the terminal can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorDepth {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+
+/// Works out the colour depth from `COLORTERM` and `TERM`, assuming the basic
+/// 16 colours when neither says otherwise.
+pub fn detect_depth() -> ColorDepth {
+    static DEPTH: OnceLock<ColorDepth> = OnceLock::new();
+    *DEPTH.get_or_init(|| {
+        let colorterm = env::var("COLORTERM").unwrap_or_default();
+        let term = env::var("TERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            ColorDepth::TrueColor
+        } else if term.contains("256color") {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        }
+    })
+}
+
+/// Maps a colour to one that reads well on the terminal: the bright basic
+/// colours become their darker variants on a light background, and RGB colours
+/// are brought down to what the terminal supports.
+pub fn adapt(color: Color) -> Color {
+    let color = if is_light_background() {
+        for_light_background(color)
+    } else {
+        color
+    };
+    adapt_to(color, detect_depth())
+}
+
+/// Bright yellow, cyan and friends are hard to read on white, so use the
+/// darker variant of each.
+pub fn for_light_background(color: Color) -> Color {
+    match color {
+        Color::Red => Color::DarkRed,
+        Color::Green => Color::DarkGreen,
+        Color::Yellow => Color::DarkYellow,
+        Color::Blue => Color::DarkBlue,
+        Color::Magenta => Color::DarkMagenta,
+        Color::Cyan => Color::DarkCyan,
+        Color::White => Color::Black,
+        Color::Rgb { r, g, b } => Color::Rgb {
+            r: r / 2,
+            g: g / 2,
+            b: b / 2,
+        },
+        color => color,
+    }
+}
+
+pub fn adapt_to(color: Color, depth: ColorDepth) -> Color {
+    let Color::Rgb { r, g, b } = color else {
+        return color;
+    };
+    match depth {
+        ColorDepth::TrueColor => color,
+        ColorDepth::Ansi256 => Color::AnsiValue(nearest_ansi256(r, g, b)),
+        ColorDepth::Ansi16 => nearest_ansi16(r, g, b),
+    }
+}
+
+/// Levels of the 6×6×6 colour cube in the 256-colour palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// The closest entry in the xterm 256-colour palette, from either the colour
+/// cube (16–231) or the greyscale ramp (232–255).
+fn nearest_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    let level = |c: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|&idx| CUBE_LEVELS[idx].abs_diff(c))
+            .unwrap()
+    };
+    let (ri, gi, bi) = (level(r), level(g), level(b));
+    let cube = (CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]);
+    let cube_index = 16 + 36 * ri + 6 * gi + bi;
+
+    let average = (r as usize + g as usize + b as usize) / 3;
+    let grey_step = (average.saturating_sub(8) / 10).min(23);
+    let grey_level = (8 + 10 * grey_step) as u8;
+    let grey = (grey_level, grey_level, grey_level);
+
+    if distance((r, g, b), grey) < distance((r, g, b), cube) {
+        (232 + grey_step) as u8
+    } else {
+        cube_index as u8
+    }
+}
+
+/// The basic ANSI colours with their usual (xterm) RGB values.
+const ANSI16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::DarkRed, (205, 0, 0)),
+    (Color::DarkGreen, (0, 205, 0)),
+    (Color::DarkYellow, (205, 205, 0)),
+    (Color::DarkBlue, (0, 0, 238)),
+    (Color::DarkMagenta, (205, 0, 205)),
+    (Color::DarkCyan, (0, 205, 205)),
+    (Color::Grey, (229, 229, 229)),
+    (Color::DarkGrey, (127, 127, 127)),
+    (Color::Red, (255, 0, 0)),
+    (Color::Green, (0, 255, 0)),
+    (Color::Yellow, (255, 255, 0)),
+    (Color::Blue, (92, 92, 255)),
+    (Color::Magenta, (255, 0, 255)),
+    (Color::Cyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+fn nearest_ansi16(r: u8, g: u8, b: u8) -> Color {
+    ANSI16
+        .iter()
+        .min_by_key(|(_, rgb)| distance((r, g, b), *rgb))
+        .map(|(color, _)| *color)
+        .unwrap()
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rgb_maps_to_the_256_colour_cube() {
+        assert_eq!(nearest_ansi256(95, 135, 215), 68);
+        assert_eq!(nearest_ansi256(255, 0, 0), 196);
+        assert_eq!(nearest_ansi256(0, 0, 0), 16);
+        assert_eq!(nearest_ansi256(255, 255, 255), 231);
+    }
+
+    #[test]
+    fn greys_map_to_the_greyscale_ramp() {
+        assert_eq!(nearest_ansi256(128, 128, 128), 244);
+        assert_eq!(nearest_ansi256(30, 30, 30), 234);
+    }
+
+    #[test]
+    fn rgb_maps_to_the_nearest_basic_colour() {
+        assert_eq!(nearest_ansi16(250, 10, 10), Color::Red);
+        assert_eq!(nearest_ansi16(0, 0, 200), Color::DarkBlue);
+        assert_eq!(nearest_ansi16(120, 120, 120), Color::DarkGrey);
+        assert_eq!(nearest_ansi16(95, 135, 215), Color::Blue);
+    }
+
+    #[test]
+    fn only_rgb_is_brought_down() {
+        let moved = Color::Rgb {
+            r: 95,
+            g: 135,
+            b: 215,
+        };
+        assert_eq!(adapt_to(moved, ColorDepth::TrueColor), moved);
+        assert_eq!(adapt_to(moved, ColorDepth::Ansi256), Color::AnsiValue(68));
+        assert_eq!(adapt_to(moved, ColorDepth::Ansi16), Color::Blue);
+        assert_eq!(adapt_to(Color::Green, ColorDepth::Ansi16), Color::Green);
+    }
+
+    #[test]
+    fn a_light_hint_selects_the_light_palette() {
+        assert!(is_light(Background::Light, None));
+        assert!(!is_light(Background::Dark, Some("0;15")));
+        assert!(is_light(Background::Auto, Some("0;15")));
+        assert!(is_light(Background::Auto, Some("0;default;7")));
+        assert!(!is_light(Background::Auto, Some("15;0")));
+        assert!(!is_light(Background::Auto, None));
+    }
+
+    #[test]
+    fn the_light_palette_darkens_bright_colours() {
+        assert_eq!(for_light_background(Color::Yellow), Color::DarkYellow);
+        assert_eq!(for_light_background(Color::White), Color::Black);
+        assert_eq!(for_light_background(Color::DarkGrey), Color::DarkGrey);
+        assert_eq!(
+            for_light_background(Color::Rgb {
+                r: 95,
+                g: 135,
+                b: 215
+            }),
+            Color::Rgb {
+                r: 47,
+                g: 67,
+                b: 107
+            }
+        );
+    }
+}
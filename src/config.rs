@@ -0,0 +1,191 @@
+use crate::args::{Args, Background, View};
+use crate::error::GiffError;
+use crate::giff;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, ValueEnum};
+use std::collections::HashMap;
+
+/// Fills in options not given on the command line from `git config giff.*`:
+///
+/// - `giff.branch`
+/// - `giff.view`: `side-by-side`, `unified` or `inline`
+/// - `giff.background`: `auto`, `light` or `dark`
+/// - `giff.maxLines`, `giff.wrapAt`
+/// - `giff.showWhitespace`, `giff.onlyChanges`, `giff.colorMoved`,
+///   `giff.ignoreBlankLines`, `giff.legend`, `giff.statBars`
+pub fn apply_git_config(args: &mut Args, matches: &ArgMatches) -> Result<(), GiffError> {
+    let config = giff::git_output(&["config", "-z", "--get-regexp", r"^giff\."])
+        .map(|output| parse_config(&output))
+        .unwrap_or_default();
+    apply(args, matches, &config)
+}
+
+/// The `giff.*` settings, keyed by their lowercased name as git prints them.
+/// A key with no `=` has no value.
+type Config = HashMap<String, Option<String>>;
+
+/// Parses `git config -z --get-regexp` output: entries end in NUL and the
+/// value, if any, follows the key after a newline. Later entries win, as with
+/// `git config --get`.
+fn parse_config(output: &str) -> Config {
+    output
+        .split('\0')
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match entry.split_once('\n') {
+            Some((key, value)) => (key.to_lowercase(), Some(value.to_string())),
+            None => (entry.to_lowercase(), None),
+        })
+        .collect()
+}
+
+fn apply(args: &mut Args, matches: &ArgMatches, config: &Config) -> Result<(), GiffError> {
+    let unset = |id: &str| matches.value_source(id) != Some(ValueSource::CommandLine);
+    let get = |key: &str| {
+        config
+            .get(&key.to_lowercase())
+            .map(|value| value.clone().unwrap_or_default())
+    };
+
+    if unset("branch") {
+        if let Some(branch) = get("giff.branch") {
+            args.branch = branch;
+        }
+    }
+    if unset("view") && !args.inline {
+        if let Some(view) = get("giff.view") {
+            args.view = View::from_str(&view, true)
+                .map_err(|_| invalid("giff.view", &view, "side-by-side, unified or inline"))?;
+        }
+    }
+    if unset("background") {
+        if let Some(background) = get("giff.background") {
+            args.background = Background::from_str(&background, true)
+                .map_err(|_| invalid("giff.background", &background, "auto, light or dark"))?;
+        }
+    }
+    if unset("max_lines") {
+        if let Some(lines) = get("giff.maxLines") {
+            args.max_lines = Some(parse_number("giff.maxLines", &lines)?);
+        }
+    }
+    if unset("wrap_at") {
+        if let Some(cols) = get("giff.wrapAt") {
+            args.wrap_at = Some(parse_number("giff.wrapAt", &cols)?);
+        }
+    }
+
+    let flags = [
+        (
+            "show_whitespace",
+            "giff.showWhitespace",
+            &mut args.show_whitespace,
+        ),
+        ("only_changes", "giff.onlyChanges", &mut args.only_changes),
+        ("color_moved", "giff.colorMoved", &mut args.color_moved),
+        (
+            "ignore_blank_lines",
+            "giff.ignoreBlankLines",
+            &mut args.ignore_blank_lines,
+        ),
+        (
+            "ignore_all_space",
+            "giff.ignoreAllSpace",
+            &mut args.ignore_all_space,
+        ),
+        ("legend", "giff.legend", &mut args.legend),
+        ("stat_bars", "giff.statBars", &mut args.stat_bars),
+    ];
+    for (id, key, flag) in flags {
+        if unset(id) {
+            if let Some(value) = config.get(&key.to_lowercase()) {
+                *flag = parse_bool(key, value.as_deref())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// A boolean setting, accepting what git does: `true`, `yes`, `on`, a nonzero
+/// number or no value at all for true, and `false`, `no`, `off`, `0` or an
+/// empty value for false.
+fn parse_bool(key: &str, value: Option<&str>) -> Result<bool, GiffError> {
+    let Some(value) = value else {
+        return Ok(true);
+    };
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "on" => Ok(true),
+        "false" | "no" | "off" | "" => Ok(false),
+        number => number
+            .parse::<i64>()
+            .map(|n| n != 0)
+            .map_err(|_| invalid(key, value, "true or false")),
+    }
+}
+
+fn parse_number(key: &str, value: &str) -> Result<usize, GiffError> {
+    value.parse().map_err(|_| invalid(key, value, "a number"))
+}
+
+fn invalid(key: &str, value: &str, expected: &str) -> GiffError {
+    GiffError::Invalid(format!(
+        "invalid value '{}' for {} in git config, expected {}",
+        value, key, expected
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{CommandFactory, FromArgMatches};
+
+    /// Parses `cli` and applies `config` to it, as `giff` does at startup.
+    fn configured(cli: &[&str], config: &str) -> Args {
+        let matches = Args::command().try_get_matches_from(cli).unwrap();
+        let mut args = Args::from_arg_matches(&matches).unwrap();
+        apply(&mut args, &matches, &parse_config(config)).unwrap();
+        args
+    }
+
+    #[test]
+    fn config_applies_only_where_the_command_line_is_silent() {
+        let config = "giff.view\nunified\0giff.maxlines\n20\0";
+        let args = configured(&["giff"], config);
+        assert_eq!(args.view, View::Unified);
+        assert_eq!(args.max_lines, Some(20));
+
+        let args = configured(&["giff", "--view", "inline", "--max-lines", "5"], config);
+        assert_eq!(args.view, View::Inline);
+        assert_eq!(args.max_lines, Some(5));
+    }
+
+    #[test]
+    fn booleans_read_like_git_reads_them() {
+        let config = "giff.legend\0giff.statbars\n1\0giff.onlychanges\nYes\0";
+        let args = configured(&["giff"], config);
+        assert!(args.legend && args.stat_bars && args.only_changes);
+
+        for value in ["off", "No", "0", ""] {
+            assert!(!parse_bool("giff.legend", Some(value)).unwrap());
+        }
+        let err = parse_bool("giff.legend", Some("maybe")).unwrap_err();
+        assert!(matches!(err, GiffError::Invalid(_)));
+    }
+
+    #[test]
+    fn later_entries_win() {
+        let config = parse_config("giff.branch\nmain\0giff.Branch\ndevelop\0");
+        assert_eq!(config["giff.branch"].as_deref(), Some("develop"));
+    }
+
+    #[test]
+    fn bad_numbers_are_invalid() {
+        assert_eq!(parse_number("giff.maxLines", "20").unwrap(), 20);
+        let err = parse_number("giff.maxLines", "ten").unwrap_err();
+        assert!(matches!(&err, GiffError::Invalid(_)));
+        assert_eq!(
+            err.to_string(),
+            "invalid value 'ten' for giff.maxLines in git config, expected a number"
+        );
+    }
+}
@@ -0,0 +1,309 @@
+use crate::args::{Args, StashPart, StashSelector};
+use crate::giff;
+
+/// The `git` arguments that produce a diff, built up one option at a time.
+///
+/// Every way giff obtains a diff from git goes through this, so a flag is
+/// handled in one place whatever the revisions are.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiffOptions {
+    command: Vec<String>,
+    revisions: Vec<String>,
+    diff_filter: Option<String>,
+    ignore_blank_lines: bool,
+    ignore_all_space: bool,
+    reverse: bool,
+    ignore_matching_lines: Vec<String>,
+    paths: Vec<String>,
+}
+
+impl DiffOptions {
+    /// `git diff base..head`.
+    pub fn range(base: &str, head: &str) -> Self {
+        DiffOptions {
+            command: vec!["diff".to_string()],
+            revisions: vec![format!("{}..{}", base, head)],
+            ..Default::default()
+        }
+    }
+
+    /// `git diff base`: the working tree against `base`.
+    pub fn working_tree(base: &str) -> Self {
+        DiffOptions {
+            command: vec!["diff".to_string()],
+            revisions: vec![base.to_string()],
+            ..Default::default()
+        }
+    }
+
+    /// `git diff --cached base`: the index against `base`.
+    pub fn staged(base: &str) -> Self {
+        DiffOptions {
+            command: vec!["diff".to_string(), "--cached".to_string()],
+            revisions: vec![base.to_string()],
+            ..Default::default()
+        }
+    }
+
+    /// `git diff`: the working tree's unstaged changes, as a patch `git apply`
+    /// accepts whatever the user's config says about colours, external diff
+    /// drivers or prefixes.
+    pub fn unstaged() -> Self {
+        DiffOptions {
+            command: [
+                "diff",
+                "--no-color",
+                "--no-ext-diff",
+                "--src-prefix=a/",
+                "--dst-prefix=b/",
+            ]
+            .map(String::from)
+            .to_vec(),
+            ..Default::default()
+        }
+    }
+
+    /// One part of a stash entry.
+    ///
+    /// A stash commit's first parent is the commit it was made on, its second
+    /// parent holds the index, and an optional third parent holds untracked
+    /// files.
+    pub fn stash(stash: &StashSelector) -> Self {
+        let rev = stash.rev();
+        let (command, revisions) = match stash.part {
+            StashPart::WorkTree => (vec!["stash", "show", "-p"], vec![rev]),
+            StashPart::Index => (
+                vec!["diff"],
+                vec![format!("{}^1", rev), format!("{}^2", rev)],
+            ),
+            // The untracked commit has no parent, so `show` lists every file as new.
+            StashPart::Untracked => (vec!["show", "--format="], vec![format!("{}^3", rev)]),
+        };
+        DiffOptions {
+            command: command.into_iter().map(String::from).collect(),
+            revisions,
+            ..Default::default()
+        }
+    }
+
+    /// The options given on the command line.
+    pub fn from_args(args: &Args) -> Self {
+        let options = match (&args.stash, &args.staged) {
+            (Some(stash), _) => DiffOptions::stash(stash),
+            (None, Some(base)) => DiffOptions::staged(base),
+            (None, None) if args.worktree.is_some() => DiffOptions::working_tree(&args.branch),
+            (None, None) => {
+                let (base, head) = giff::revisions(args);
+                DiffOptions::range(&base, &head)
+            }
+        };
+        let options = match &args.diff_filter {
+            Some(filter) => options.diff_filter(filter),
+            None => options,
+        };
+        options
+            .ignore_blank_lines(args.ignore_blank_lines)
+            .ignore_all_space(args.ignore_all_space)
+            .reverse(args.reverse)
+            .ignore_matching_lines(&args.ignore_matching_lines)
+            .paths(&args.paths)
+    }
+
+    /// Only show files with these change kinds (`--diff-filter`).
+    pub fn diff_filter(mut self, filter: &str) -> Self {
+        self.diff_filter = Some(filter.to_string());
+        self
+    }
+
+    /// Ignore changes whose lines are all blank (`--ignore-blank-lines`).
+    pub fn ignore_blank_lines(mut self, ignore: bool) -> Self {
+        self.ignore_blank_lines = ignore;
+        self
+    }
+
+    /// Ignore whitespace when comparing lines (`--ignore-all-space`).
+    pub fn ignore_all_space(mut self, ignore: bool) -> Self {
+        self.ignore_all_space = ignore;
+        self
+    }
+
+    /// Swap the two sides (`-R`).
+    pub fn reverse(mut self, reverse: bool) -> Self {
+        self.reverse = reverse;
+        self
+    }
+
+    /// Ignore changes whose lines all match one of `patterns` (`-I`).
+    pub fn ignore_matching_lines<S: AsRef<str>>(
+        mut self,
+        patterns: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.ignore_matching_lines
+            .extend(patterns.into_iter().map(|p| p.as_ref().to_string()));
+        self
+    }
+
+    /// Restrict the diff to these paths.
+    pub fn paths<S: AsRef<str>>(mut self, paths: impl IntoIterator<Item = S>) -> Self {
+        self.paths
+            .extend(paths.into_iter().map(|p| p.as_ref().to_string()));
+        self
+    }
+
+    /// The argument vector to pass to `git`.
+    pub fn to_args(&self) -> Vec<String> {
+        let mut git_args = self.command.clone();
+        if let Some(filter) = &self.diff_filter {
+            git_args.push(format!("--diff-filter={}", filter));
+        }
+        if self.reverse {
+            // `-R` also swaps the `a/` and `b/` prefixes the parser expects,
+            // so swap them back.
+            git_args.extend(["-R", "--src-prefix=b/", "--dst-prefix=a/"].map(String::from));
+        }
+        if self.ignore_blank_lines {
+            git_args.push("--ignore-blank-lines".to_string());
+        }
+        if self.ignore_all_space {
+            git_args.push("--ignore-all-space".to_string());
+        }
+        for pattern in &self.ignore_matching_lines {
+            git_args.push(format!("--ignore-matching-lines={}", pattern));
+        }
+        git_args.extend(self.revisions.iter().cloned());
+        if !self.paths.is_empty() {
+            git_args.push("--".to_string());
+            git_args.extend(self.paths.iter().cloned());
+        }
+        git_args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn stash(part: StashPart) -> Vec<String> {
+        DiffOptions::stash(&StashSelector { index: 1, part }).to_args()
+    }
+
+    #[test]
+    fn revisions_follow_the_options() {
+        assert_eq!(
+            DiffOptions::range("main", "HEAD").to_args(),
+            ["diff", "main..HEAD"]
+        );
+        assert_eq!(
+            DiffOptions::working_tree("main").to_args(),
+            ["diff", "main"]
+        );
+        assert_eq!(
+            DiffOptions::staged("v1.0").to_args(),
+            ["diff", "--cached", "v1.0"]
+        );
+    }
+
+    #[test]
+    fn each_stash_part_has_its_own_command() {
+        assert_eq!(
+            stash(StashPart::WorkTree),
+            ["stash", "show", "-p", "stash@{1}"]
+        );
+        assert_eq!(
+            stash(StashPart::Index),
+            ["diff", "stash@{1}^1", "stash@{1}^2"]
+        );
+        assert_eq!(
+            stash(StashPart::Untracked),
+            ["show", "--format=", "stash@{1}^3"]
+        );
+    }
+
+    #[test]
+    fn reverse_swaps_the_prefixes_back() {
+        assert_eq!(
+            DiffOptions::range("a", "b").reverse(true).to_args(),
+            ["diff", "-R", "--src-prefix=b/", "--dst-prefix=a/", "a..b"]
+        );
+    }
+
+    #[test]
+    fn filters_and_paths() {
+        let options = DiffOptions::range("a", "b")
+            .diff_filter("AM")
+            .ignore_blank_lines(true)
+            .ignore_all_space(true)
+            .ignore_matching_lines(["^#", "TODO"])
+            .paths(["src", "README.md"]);
+        assert_eq!(
+            options.to_args(),
+            [
+                "diff",
+                "--diff-filter=AM",
+                "--ignore-blank-lines",
+                "--ignore-all-space",
+                "--ignore-matching-lines=^#",
+                "--ignore-matching-lines=TODO",
+                "a..b",
+                "--",
+                "src",
+                "README.md",
+            ]
+        );
+    }
+
+    #[test]
+    fn no_paths_leaves_out_the_separator() {
+        assert!(!DiffOptions::staged("HEAD")
+            .to_args()
+            .contains(&"--".to_string()));
+    }
+
+    #[test]
+    fn unstaged_fixes_the_prefixes() {
+        assert_eq!(
+            DiffOptions::unstaged().paths(["f"]).to_args(),
+            [
+                "diff",
+                "--no-color",
+                "--no-ext-diff",
+                "--src-prefix=a/",
+                "--dst-prefix=b/",
+                "--",
+                "f",
+            ]
+        );
+    }
+
+    #[test]
+    fn command_line_options_are_carried_over() {
+        let mut args = Args::try_parse_from([
+            "giff",
+            "--staged",
+            "HEAD",
+            "-R",
+            "-I",
+            "^#",
+            "--diff-filter",
+            "d",
+        ])
+        .unwrap();
+        args.paths = vec!["src".to_string()];
+        assert_eq!(
+            DiffOptions::from_args(&args).to_args(),
+            [
+                "diff",
+                "--cached",
+                "--diff-filter=d",
+                "-R",
+                "--src-prefix=b/",
+                "--dst-prefix=a/",
+                "--ignore-matching-lines=^#",
+                "HEAD",
+                "--",
+                "src",
+            ]
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use std::io;
+use thiserror::Error;
+
+/// Why giff couldn't produce a diff.
+#[derive(Debug, Error)]
+pub enum GiffError {
+    /// The git executable (or `sh`, for `--diff-command`) couldn't be started.
+    #[error("Could not run `{program}`: {source}")]
+    GitNotFound {
+        program: String,
+        #[source]
+        source: io::Error,
+    },
+
+    /// Git was run outside a repository.
+    #[error("Not a git repository: {0}")]
+    NotARepo(String),
+
+    /// A revision given on the command line doesn't exist.
+    #[error("Unknown revision '{0}'")]
+    BadRevision(String),
+
+    /// Output that should have been a diff wasn't one git would print.
+    #[error("{0}")]
+    Parse(String),
+
+    /// A command exited unsuccessfully.
+    #[error("`{command}` failed: {reason}")]
+    CommandFailed { command: String, reason: String },
+
+    /// Options or configuration that can't be acted on.
+    #[error("{0}")]
+    Invalid(String),
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::giff;
+    use std::process::Command;
+
+    #[test]
+    fn failures_outside_a_repository_are_not_a_repo() {
+        let output = Command::new("git")
+            .args(["-C", "/", "rev-parse", "--git-dir"])
+            .env("GIT_CEILING_DIRECTORIES", "/")
+            .output()
+            .unwrap();
+        assert!(matches!(
+            giff::command_error("git rev-parse", &output),
+            GiffError::NotARepo(_)
+        ));
+    }
+
+    #[test]
+    fn other_failures_carry_git_stderr() {
+        let output = Command::new("git")
+            .args(["no-such-command"])
+            .output()
+            .unwrap();
+        let err = giff::command_error("git no-such-command", &output);
+        let GiffError::CommandFailed { command, reason } = &err else {
+            panic!("expected CommandFailed, got {:?}", err);
+        };
+        assert_eq!(command, "git no-such-command");
+        assert!(reason.contains("no-such-command"));
+        assert!(err
+            .to_string()
+            .starts_with("`git no-such-command` failed: "));
+    }
+
+    #[test]
+    fn a_program_that_cannot_start_is_git_not_found() {
+        let mut command = Command::new("giff-no-such-git");
+        let err = command.output().unwrap_err();
+        let err = giff::spawn_error(&command, err);
+        assert!(
+            matches!(&err, GiffError::GitNotFound { program, .. } if program == "giff-no-such-git")
+        );
+    }
+
+    #[test]
+    fn io_errors_convert() {
+        let err: GiffError = io::Error::new(io::ErrorKind::BrokenPipe, "pipe").into();
+        assert!(matches!(err, GiffError::Io(_)));
+        assert_eq!(
+            GiffError::BadRevision("nope".to_string()).to_string(),
+            "Unknown revision 'nope'"
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use crate::parser::FileChanges;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// Writes each file's patch to `<dir>/<path>.patch`, mirroring the repository
+/// layout, and returns how many were written. Each patch applies on its own
+/// with `git apply`.
+pub fn write_patches(dir: &Path, file_changes: &FileChanges) -> io::Result<usize> {
+    for (file, change) in file_changes {
+        let path = dir.join(patch_file_name(file));
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut patch = change.patch.clone();
+        if !patch.ends_with('\n') {
+            patch.push('\n');
+        }
+        fs::write(path, patch)?;
+    }
+    Ok(file_changes.len())
+}
+
+/// The relative path a file's patch is written to. Only normal components are
+/// kept so a path can't escape the output directory.
+fn patch_file_name(file: &str) -> PathBuf {
+    let mut name: PathBuf = Path::new(file)
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part),
+            _ => None,
+        })
+        .collect();
+    let file_name = match name.file_name() {
+        Some(file_name) => format!("{}.patch", file_name.to_string_lossy()),
+        None => "unnamed.patch".to_string(),
+    };
+    name.set_file_name(file_name);
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_diff_output;
+    use crate::test_repo;
+
+    #[test]
+    fn file_names_stay_inside_the_directory() {
+        assert_eq!(
+            patch_file_name("src/main.rs"),
+            Path::new("src/main.rs.patch")
+        );
+        assert_eq!(
+            patch_file_name("../../etc/passwd"),
+            Path::new("etc/passwd.patch")
+        );
+        assert_eq!(patch_file_name("/abs/path"), Path::new("abs/path.patch"));
+        assert_eq!(patch_file_name("a/./b"), Path::new("a/b.patch"));
+        assert_eq!(patch_file_name(".."), Path::new("unnamed.patch"));
+    }
+
+    #[test]
+    fn written_patches_apply() {
+        let repo = test_repo::init();
+        // Undoes the whole history, so each patch applies to the checkout.
+        let diff = test_repo::git(repo, &["diff", "HEAD", "root"]);
+        let file_changes = parse_diff_output(&diff);
+
+        let dir = std::env::temp_dir().join(format!("giff-export-test-{}", std::process::id()));
+        let written = write_patches(&dir, &file_changes).unwrap();
+        assert_eq!(written, 3);
+        for file in file_changes.keys() {
+            let patch = dir.join(patch_file_name(file));
+            test_repo::git(repo, &["apply", "--check", patch.to_str().unwrap()]);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,15 +1,854 @@
-use std::error::Error;
-use std::process::Command;
+use crate::args::{Args, StashPart};
+use crate::cache;
+use crate::diff_options::DiffOptions;
+use crate::error::GiffError;
+use crate::parser;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::OnceLock;
 
-pub fn get_diff_output(branch: &str) -> Result<String, Box<dyn Error>> {
-    let output = Command::new("git")
-        .args(["diff", &format!("{}..HEAD", branch)])
-        .output()?;
+static WORK_DIR: OnceLock<PathBuf> = OnceLock::new();
+static GIT_PATH: OnceLock<PathBuf> = OnceLock::new();
 
+/// Runs every git command in `dir`, like `git -C <dir>`.
+pub fn set_work_dir(dir: PathBuf) {
+    let _ = WORK_DIR.set(dir);
+}
+
+/// Runs this git executable instead of the `git` on `PATH`.
+pub fn set_git_path(path: PathBuf) {
+    let _ = GIT_PATH.set(path);
+}
+
+/// A `git` command for the repository being diffed. `GIT_DIR` and
+/// `GIT_WORK_TREE` reach git through the inherited environment.
+pub fn git() -> Command {
+    let mut command = match GIT_PATH.get() {
+        Some(path) => Command::new(path),
+        None => Command::new("git"),
+    };
+    if let Some(dir) = WORK_DIR.get() {
+        command.arg("-C").arg(dir);
+    }
+    command
+}
+
+/// Arguments passed to `git` to produce the diff requested on the command line.
+pub fn diff_args(args: &Args) -> Vec<String> {
+    DiffOptions::from_args(args).to_args()
+}
+
+/// Labels for the base and head columns.
+pub fn labels(args: &Args) -> (String, String) {
+    let (base, head) = match (&args.stash, &args.staged) {
+        (Some(stash), _) => {
+            let rev = stash.rev();
+            match stash.part {
+                StashPart::WorkTree => (format!("{}^", rev), rev),
+                StashPart::Index => (format!("{}^", rev), format!("{} index", rev)),
+                StashPart::Untracked => ("(none)".to_string(), format!("{} untracked", rev)),
+            }
+        }
+        (None, Some(base)) => (ref_label(base, args.abbrev), "Index".to_string()),
+        (None, None) => {
+            let (base, head) = revisions(args);
+            match &args.worktree {
+                Some(path) => (
+                    ref_label(&base, args.abbrev),
+                    format!("{} (working tree)", path.display()),
+                ),
+                None => (ref_label(&base, args.abbrev), ref_label(&head, args.abbrev)),
+            }
+        }
+    };
+    if args.reverse {
+        (head, base)
+    } else {
+        (base, head)
+    }
+}
+
+/// The base and head revisions being compared.
+pub fn revisions(args: &Args) -> (String, String) {
+    args.range
+        .clone()
+        .unwrap_or_else(|| (args.branch.clone(), "HEAD".to_string()))
+}
+
+/// Applies the `BASE` and `HEAD|PATH` positionals: `BASE` replaces
+/// `--branch`, and the second is taken as the head revision or, when it names
+/// an existing path rather than a revision, as a path to restrict the diff to.
+/// Pathspecs after `--` are added to the paths untouched. Two blobs (e.g.
+/// `HEAD:src/main.rs` or a blob SHA) are compared with each other.
+pub fn resolve_positionals(args: &mut Args) -> Result<(), GiffError> {
+    let base_is_blob = args.base.as_deref().is_some_and(is_blob);
+    if let Some(base) = args.base.take() {
+        args.branch = base;
+    }
+    args.paths.append(&mut args.pathspecs);
+    let Some(target) = args.head_or_path.take() else {
+        if base_is_blob {
+            return Err(GiffError::Invalid(format!(
+                "'{}' is a blob; give a second blob to compare it with",
+                args.branch
+            )));
+        }
+        return Ok(());
+    };
+
+    if base_is_blob || is_blob(&target) {
+        if let Some(rev) = [&args.branch, &target]
+            .into_iter()
+            .find(|rev| !is_blob(rev))
+        {
+            return Err(GiffError::Invalid(format!(
+                "'{}' is not a blob; a blob can only be compared with another blob",
+                rev
+            )));
+        }
+        args.range = Some((args.branch.clone(), target));
+        return Ok(());
+    }
+
+    let is_path = match WORK_DIR.get() {
+        Some(dir) => dir.join(&target).exists(),
+        None => Path::new(&target).exists(),
+    };
+    let is_rev = rev_exists(&format!("{}^{{commit}}", target));
+
+    match (is_rev, is_path) {
+        (true, true) => Err(GiffError::Invalid(format!(
+            "'{}' is both a revision and a path; write ./{} to mean the path",
+            target, target
+        ))),
+        (false, true) => {
+            args.paths.push(target);
+            Ok(())
+        }
+        (true, false) => {
+            if args.last
+                || args.pick
+                || args.pr
+                || args.since.is_some()
+                || args.author.is_some()
+                || args.worktree.is_some()
+            {
+                return Err(GiffError::Invalid(format!(
+                    "'{}' is a revision, which can't be combined with --last, --pick, --pr, --since, --author or --worktree",
+                    target
+                )));
+            }
+            args.range = Some((args.branch.clone(), target));
+            Ok(())
+        }
+        (false, false) => Err(GiffError::Invalid(format!(
+            "'{}' is neither a revision nor a path",
+            target
+        ))),
+    }
+}
+
+/// With `--follow`, adds the names the one restricted path had earlier in the
+/// range, so the diff still covers it across renames.
+pub fn follow_renames(args: &mut Args) -> Result<(), GiffError> {
+    let [path] = args.paths.as_slice() else {
+        return Err(GiffError::Invalid(
+            "--follow needs exactly one path to follow".to_string(),
+        ));
+    };
+    let (base, head) = revisions(args);
+    let range = format!("{}..{}", base, head);
+    let log = git_output(&[
+        "log",
+        "--follow",
+        "--name-status",
+        "-z",
+        "--format=",
+        &range,
+        "--",
+        path,
+    ])
+    .ok_or_else(|| GiffError::CommandFailed {
+        command: format!("git log --follow {} -- {}", range, path),
+        reason: "no history for the path".to_string(),
+    })?;
+
+    for name in followed_names(&log) {
+        if !args.paths.contains(&name) {
+            args.paths.push(name);
+        }
+    }
+    Ok(())
+}
+
+/// Every path in `git log --name-status -z` output, including the sources of
+/// renames and copies, which `--name-only` leaves out.
+fn followed_names(log: &str) -> Vec<String> {
+    let mut fields = log
+        .split('\0')
+        .map(|field| field.trim_start_matches('\n'))
+        .filter(|field| !field.is_empty());
+    let mut names = Vec::new();
+    while let Some(status) = fields.next() {
+        let paths = if status.starts_with(['R', 'C']) { 2 } else { 1 };
+        names.extend(fields.by_ref().take(paths).map(String::from));
+    }
+    names
+}
+
+/// Checks that `path` is the top of a git working tree, such as a linked
+/// worktree.
+pub fn check_worktree(path: &Path) -> Result<(), GiffError> {
+    let mut command = git();
+    let toplevel = command
+        .arg("-C")
+        .arg(path)
+        .args(["rev-parse", "--show-toplevel"])
+        .output()
+        .map_err(|err| spawn_error(&command, err))?;
+    if !toplevel.status.success() {
+        return Err(GiffError::NotARepo(path.display().to_string()));
+    }
+    Ok(())
+}
+
+/// Resolves options that select a range other than `<branch>..HEAD`, or
+/// returns `None` if none were given.
+pub fn resolve_range(args: &Args) -> Result<Option<(String, String)>, GiffError> {
+    if args.last {
+        return Ok(Some(last_commit("HEAD")));
+    }
+    if args.pr {
+        let default_branch = default_branch().ok_or_else(|| {
+            GiffError::Invalid(
+                "Could not find the default branch: set it with `git remote set-head origin --auto`"
+                    .to_string(),
+            )
+        })?;
+        let merge_base = git_output(&["merge-base", &default_branch, "HEAD"]).ok_or_else(|| {
+            GiffError::Invalid(format!(
+                "HEAD has no common history with {}",
+                default_branch
+            ))
+        })?;
+        return Ok(Some((merge_base, "HEAD".to_string())));
+    }
+
+    resolve_log_range(args)
+}
+
+/// The range showing just `commit`'s changes. A root commit is compared
+/// against the empty tree.
+fn last_commit(commit: &str) -> (String, String) {
+    let parent = format!("{}~1", commit);
+    let base = if rev_exists(&parent) {
+        parent
+    } else {
+        empty_tree()
+    };
+    (base, commit.to_string())
+}
+
+/// The branch pull requests merge into: what `origin/HEAD` points at, or else
+/// `origin/main` or `origin/master`, whichever exists.
+fn default_branch() -> Option<String> {
+    git_output(&[
+        "symbolic-ref",
+        "--short",
+        "--quiet",
+        "refs/remotes/origin/HEAD",
+    ])
+    .or_else(|| {
+        ["origin/main", "origin/master"]
+            .into_iter()
+            .find(|branch| rev_exists(branch))
+            .map(String::from)
+    })
+}
+
+/// Resolves `--since`/`--author` to the range covering the matching commits:
+/// from the parent of the oldest to the newest. Returns `None` if neither
+/// option was given.
+fn resolve_log_range(args: &Args) -> Result<Option<(String, String)>, GiffError> {
+    if args.since.is_none() && args.author.is_none() {
+        return Ok(None);
+    }
+
+    let mut log_args = vec!["log".to_string(), "--format=%H".to_string()];
+    if let Some(since) = &args.since {
+        log_args.push(format!("--since={}", since));
+    }
+    if let Some(author) = &args.author {
+        log_args.push(format!("--author={}", author));
+    }
+    let log_args: Vec<&str> = log_args.iter().map(String::as_str).collect();
+
+    let commits = git_output(&log_args).unwrap_or_default();
+    let mut commits = commits.lines();
+    let newest = commits.next().ok_or_else(|| {
+        GiffError::Invalid("No commits match the given --since/--author filters".to_string())
+    })?;
+    let oldest = commits.last().unwrap_or(newest);
+
+    Ok(Some((parent_or_empty_tree(oldest), newest.to_string())))
+}
+
+/// The SHA of `commit`'s parent, or the empty tree when it is a root commit.
+pub fn parent_or_empty_tree(commit: &str) -> String {
+    git_output(&["rev-parse", "--verify", "--quiet", &format!("{}^", commit)])
+        .unwrap_or_else(empty_tree)
+}
+
+/// The commits in `base..head` that touch `paths`, oldest first, one
+/// `<sha> <subject>` line each.
+pub fn range_commits(base: &str, head: &str, paths: &[String]) -> String {
+    let range = format!("{}..{}", base, head);
+    let mut log_args = vec!["log", "--reverse", "--format=%H %s", &range, "--"];
+    log_args.extend(paths.iter().map(String::as_str));
+    git_output(&log_args).unwrap_or_default()
+}
+
+/// The id of the empty tree, which is what a root commit is diffed against.
+fn empty_tree() -> String {
+    git_output(&["hash-object", "-t", "tree", "/dev/null"])
+        .unwrap_or_else(|| "4b825dc642cb6eb9a060e54bf8d69288fbee4904".to_string())
+}
+
+/// A readable label for a commit-ish: its name plus short SHA, e.g.
+/// `main (1a2b3c4)`.
+///
+/// `HEAD` and raw SHAs are named after the branch or tag they point at; a
+/// detached `HEAD` with no such name is shown as just the short SHA.
+pub fn ref_label(rev: &str, abbrev: Option<usize>) -> String {
+    let Some(sha) = short_sha(&format!("{}^{{commit}}", rev), abbrev) else {
+        if rev == empty_tree() {
+            return "(empty tree)".to_string();
+        }
+        if is_blob(rev) {
+            return short_sha(rev, abbrev).unwrap_or_else(|| rev.to_string());
+        }
+        return rev.to_string();
+    };
+
+    let name = if rev == "HEAD" || is_hex(rev) {
+        friendly_name(rev)
+    } else {
+        Some(rev.to_string())
+    };
+
+    match name {
+        Some(name) => format!("{} ({})", name, sha),
+        None => sha,
+    }
+}
+
+/// `rev`'s SHA abbreviated to `abbrev` hex digits, or git's default length.
+/// Git may use more digits when fewer would be ambiguous.
+pub fn short_sha(rev: &str, abbrev: Option<usize>) -> Option<String> {
+    let short = match abbrev {
+        Some(len) => format!("--short={}", len),
+        None => "--short".to_string(),
+    };
+    git_output(&["rev-parse", &short, "--verify", "--quiet", rev])
+}
+
+/// How many hex digits to show of SHAs git can't abbreviate itself, such as
+/// submodule commits: `--abbrev`, or as many as git shortens `HEAD` to.
+pub fn abbrev_len(args: &Args) -> usize {
+    args.abbrev
+        .unwrap_or_else(|| short_sha("HEAD", None).map(|sha| sha.len()).unwrap_or(7))
+}
+
+/// `sha` cut to `len` hex digits.
+pub fn abbreviate(sha: &str, len: usize) -> &str {
+    &sha[..sha.len().min(len)]
+}
+
+/// The branch `HEAD` is on, or a branch or tag pointing exactly at `rev`.
+fn friendly_name(rev: &str) -> Option<String> {
+    if rev == "HEAD" {
+        if let Some(branch) = git_output(&["symbolic-ref", "--short", "--quiet", "HEAD"]) {
+            return Some(branch);
+        }
+    }
+
+    let name = git_output(&["describe", "--all", "--exact-match", rev])?;
+    Some(
+        ["heads/", "tags/", "remotes/"]
+            .iter()
+            .find_map(|prefix| name.strip_prefix(prefix))
+            .unwrap_or(&name)
+            .to_string(),
+    )
+}
+
+fn is_hex(rev: &str) -> bool {
+    rev.len() >= 4 && rev.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Runs git and returns its trimmed stdout, or `None` if it failed.
+pub fn git_output(git_args: &[&str]) -> Option<String> {
+    let output = git().args(git_args).output().ok()?;
     if !output.status.success() {
-        eprintln!("Failed to execute git diff command");
-        std::process::exit(1);
+        return None;
     }
+    Some(
+        String::from_utf8_lossy(&output.stdout)
+            .trim_end()
+            .to_string(),
+    )
+}
+
+/// Whether `rev` names a blob, i.e. one version of a file's contents.
+pub fn is_blob(rev: &str) -> bool {
+    git_output(&["cat-file", "-t", rev]).as_deref() == Some("blob")
+}
+
+/// Whether `rev` names an existing object.
+pub fn rev_exists(rev: &str) -> bool {
+    git()
+        .args(["rev-parse", "--verify", "--quiet", rev])
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
 
-    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+/// The git invocation as a copy-pasteable command line, with the program and
+/// `-C` directory [`git`] runs it with.
+pub fn command_line(args: &[String]) -> String {
+    let mut command = git();
+    command.args(args);
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|word| quote_if_needed(&word.to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The command that produces the diff: the expanded `--diff-command`, or the
+/// git invocation.
+pub fn describe_command(args: &Args) -> String {
+    match &args.diff_command {
+        Some(template) => expand_diff_command(template, args),
+        None => command_line(&diff_args(args)),
+    }
+}
+
+/// Substitutes the base and head revisions for `{left}` and `{right}`.
+fn expand_diff_command(template: &str, args: &Args) -> String {
+    let (base, head) = revisions(args);
+    let (base, head) = if args.reverse {
+        (head, base)
+    } else {
+        (base, head)
+    };
+    template
+        .replace("{left}", &shell_quote(&base))
+        .replace("{right}", &shell_quote(&head))
+}
+
+fn shell_quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', "'\\''"))
+}
+
+/// `word` as is when the shell would read it back unchanged, else quoted.
+fn quote_if_needed(word: &str) -> String {
+    let is_plain = |c: char| c.is_ascii_alphanumeric() || "-_./:=@%+,^~".contains(c);
+    if !word.is_empty() && !word.starts_with('~') && word.chars().all(is_plain) {
+        word.to_string()
+    } else {
+        shell_quote(word)
+    }
+}
+
+/// An error for a command that couldn't be started at all.
+pub fn spawn_error(command: &Command, err: std::io::Error) -> GiffError {
+    GiffError::GitNotFound {
+        program: command.get_program().to_string_lossy().to_string(),
+        source: err,
+    }
+}
+
+/// An error for a command that exited unsuccessfully, carrying its stderr.
+/// Running outside a repository gets its own error naming the directory.
+pub fn command_error(command: &str, output: &Output) -> GiffError {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if stderr.to_lowercase().contains("not a git repository") {
+        let dir = WORK_DIR
+            .get()
+            .cloned()
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default();
+        return GiffError::NotARepo(dir.display().to_string());
+    }
+    let reason = match stderr.trim_end() {
+        "" => output.status.to_string(),
+        stderr => stderr.to_string(),
+    };
+    GiffError::CommandFailed {
+        command: command.to_string(),
+        reason,
+    }
+}
+
+/// Runs a `--diff-command` through the shell and checks it printed a diff the
+/// parser understands.
+fn run_diff_command(template: &str, args: &Args) -> Result<DiffOutput, GiffError> {
+    let command = expand_diff_command(template, args);
+    let mut shell = Command::new("sh");
+    if let Some(dir) = WORK_DIR.get() {
+        shell.current_dir(dir);
+    }
+    let output = shell
+        .args(["-c", &command])
+        .output()
+        .map_err(|err| spawn_error(&shell, err))?;
+
+    if !output.status.success() {
+        return Err(command_error(&command, &output));
+    }
+
+    let diff_output = String::from_utf8_lossy(&output.stdout).to_string();
+    if !diff_output.trim().is_empty() && parser::parse_diff_output(&diff_output).is_empty() {
+        return Err(GiffError::Parse(format!(
+            "`{}` did not print a unified diff in git's format",
+            command
+        )));
+    }
+
+    Ok(diff_output.into())
+}
+
+/// The result of a successful diff command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffOutput {
+    /// Git ran fine and found no differences.
+    Empty,
+    Changes(String),
+}
+
+impl From<String> for DiffOutput {
+    fn from(diff: String) -> Self {
+        if diff.trim().is_empty() {
+            DiffOutput::Empty
+        } else {
+            DiffOutput::Changes(diff)
+        }
+    }
+}
+
+/// Runs the diff. A failing git command is an error carrying git's stderr;
+/// a successful one with no output is [`DiffOutput::Empty`].
+pub fn get_diff_output(args: &Args) -> Result<DiffOutput, GiffError> {
+    if let Some(template) = &args.diff_command {
+        return run_diff_command(template, args);
+    }
+
+    if let Some(stash) = &args.stash {
+        if !rev_exists(&stash.rev()) {
+            return Err(GiffError::Invalid(format!(
+                "No stash entry {}",
+                stash.rev()
+            )));
+        }
+        if stash.part == StashPart::Untracked && !rev_exists(&format!("{}^3", stash.rev())) {
+            return Err(GiffError::Invalid(format!(
+                "{} has no untracked files (it was not created with --include-untracked)",
+                stash.rev()
+            )));
+        }
+    }
+
+    if let Some(base) = &args.staged {
+        if !rev_exists(base) {
+            return Err(GiffError::BadRevision(base.clone()));
+        }
+    }
+
+    // Only plain branch ranges are keyed by commit SHAs; stash refs move and
+    // the index and working trees change without a new commit.
+    let cache_path = if args.no_cache
+        || args.stash.is_some()
+        || args.staged.is_some()
+        || args.worktree.is_some()
+    {
+        None
+    } else {
+        cache::cache_path(args)
+    };
+    if let Some(diff_output) = cache_path.as_deref().and_then(cache::load) {
+        return Ok(diff_output.into());
+    }
+
+    let git_args = diff_args(args);
+    let mut command = git();
+    let output = command
+        .args(&git_args)
+        .output()
+        .map_err(|err| spawn_error(&command, err))?;
+
+    if !output.status.success() {
+        let err = command_error(&command_line(&git_args), &output);
+        if matches!(err, GiffError::CommandFailed { .. })
+            && args.stash.is_none()
+            && args.staged.is_none()
+        {
+            let (base, head) = revisions(args);
+            if let Some(rev) = [base, head].into_iter().find(|rev| !rev_exists(rev)) {
+                return Err(GiffError::BadRevision(rev));
+            }
+        }
+        return Err(err);
+    }
+
+    let diff_output = String::from_utf8_lossy(&output.stdout).to_string();
+    if let Some(path) = &cache_path {
+        cache::store(path, &diff_output);
+    }
+
+    Ok(diff_output.into())
+}
+
+/// Top-level directory of the current repository.
+pub fn repo_root() -> Option<PathBuf> {
+    git_output(&["rev-parse", "--show-toplevel"]).map(PathBuf::from)
+}
+
+/// How the checked-out branch relates to its upstream, and whether the working
+/// tree has changes the diff doesn't include.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BranchState {
+    pub ahead: usize,
+    pub behind: usize,
+    pub dirty: bool,
+}
+
+impl BranchState {
+    /// Whether there's anything worth mentioning.
+    pub fn is_notable(&self) -> bool {
+        self.ahead > 0 || self.behind > 0 || self.dirty
+    }
+}
+
+impl std::fmt::Display for BranchState {
+    /// Compact form for the header, e.g. `↑2 ↓3 ✚dirty`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if self.ahead > 0 {
+            parts.push(format!("↑{}", self.ahead));
+        }
+        if self.behind > 0 {
+            parts.push(format!("↓{}", self.behind));
+        }
+        if self.dirty {
+            parts.push("✚dirty".to_string());
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+/// The current branch's state, from `git status -sb`.
+pub fn branch_state() -> Option<BranchState> {
+    git_output(&["status", "--porcelain", "-b"]).map(|status| parse_branch_state(&status))
+}
+
+/// Parses `git status --porcelain -b` output. The first line looks like
+/// `## main...origin/main [ahead 2, behind 3]`; any further line is a changed
+/// or untracked file.
+pub fn parse_branch_state(status: &str) -> BranchState {
+    let mut lines = status.lines();
+    let mut state = BranchState::default();
+
+    if let Some(header) = lines.next().and_then(|line| line.strip_prefix("## ")) {
+        let counts = header
+            .rfind('[')
+            .and_then(|start| header[start + 1..].strip_suffix(']'));
+        for count in counts.into_iter().flat_map(|counts| counts.split(", ")) {
+            if let Some(n) = count.strip_prefix("ahead ") {
+                state.ahead = n.parse().unwrap_or(0);
+            } else if let Some(n) = count.strip_prefix("behind ") {
+                state.behind = n.parse().unwrap_or(0);
+            }
+        }
+    }
+    state.dirty = lines.any(|line| !line.is_empty());
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn branch_state_reads_ahead_and_behind() {
+        let state = parse_branch_state("## main...origin/main [ahead 2, behind 3]\n");
+        assert_eq!(
+            state,
+            BranchState {
+                ahead: 2,
+                behind: 3,
+                dirty: false
+            }
+        );
+        assert_eq!(state.to_string(), "↑2 ↓3");
+    }
+
+    #[test]
+    fn branch_state_without_upstream_or_changes_is_clean() {
+        assert_eq!(parse_branch_state("## main\n"), BranchState::default());
+        assert_eq!(
+            parse_branch_state("## HEAD (no branch)\n"),
+            BranchState::default()
+        );
+    }
+
+    #[test]
+    fn changed_files_make_the_branch_dirty() {
+        let state =
+            parse_branch_state("## main...origin/main [behind 1]\n M src/main.rs\n?? new\n");
+        assert_eq!(
+            state,
+            BranchState {
+                ahead: 0,
+                behind: 1,
+                dirty: true
+            }
+        );
+        assert_eq!(state.to_string(), "↓1 ✚dirty");
+    }
+
+    #[test]
+    fn command_line_quotes_only_what_the_shell_would_change() {
+        let repo = crate::test_repo::init();
+        let args = ["diff", "main..HEAD~1", "--", "my file", "it's", "~x"].map(String::from);
+        assert_eq!(
+            command_line(&args),
+            format!(
+                r#"git -C {} diff main..HEAD~1 -- 'my file' 'it'\''s' '~x'"#,
+                repo.display()
+            )
+        );
+        assert_eq!(quote_if_needed("stash@{0}"), "'stash@{0}'");
+        assert_eq!(quote_if_needed(""), "''");
+    }
+
+    #[test]
+    fn following_a_rename_keeps_the_old_name() {
+        // `git log --follow --name-status -z` for `c d.txt`, renamed from
+        // `b.txt` within the range.
+        let log = "M\0c d.txt\0R080\0b.txt\0c d.txt\0A\0b.txt\0";
+        assert_eq!(
+            followed_names(log),
+            ["c d.txt", "b.txt", "c d.txt", "b.txt"]
+        );
+    }
+
+    fn diff_of(base: &str, head: &str) -> Result<DiffOutput, GiffError> {
+        crate::test_repo::init();
+        let mut args = Args::try_parse_from(["giff", "--no-cache"]).unwrap();
+        args.range = Some((base.to_string(), head.to_string()));
+        get_diff_output(&args)
+    }
+
+    #[test]
+    fn an_up_to_date_range_is_empty() {
+        assert!(matches!(diff_of("HEAD", "HEAD"), Ok(DiffOutput::Empty)));
+        assert!(matches!(
+            diff_of("root", "HEAD"),
+            Ok(DiffOutput::Changes(_))
+        ));
+    }
+
+    #[test]
+    fn a_missing_ref_is_a_bad_revision() {
+        assert!(matches!(
+            diff_of("HEAD", "no-such-branch"),
+            Err(GiffError::BadRevision(rev)) if rev == "no-such-branch"
+        ));
+    }
+
+    fn range_for(cli: &[&str]) -> Result<Option<(String, String)>, GiffError> {
+        crate::test_repo::init();
+        resolve_range(&Args::try_parse_from(cli).unwrap())
+    }
+
+    #[test]
+    fn since_and_author_cover_the_matching_commits() {
+        crate::test_repo::init();
+        let head = git_output(&["rev-parse", "HEAD"]).unwrap();
+        assert_eq!(
+            range_for(&["giff", "--author", "Test", "--since", "1970-01-02"]).unwrap(),
+            Some((empty_tree(), head))
+        );
+        assert!(matches!(
+            range_for(&["giff", "--author", "nobody"]),
+            Err(GiffError::Invalid(_))
+        ));
+        assert_eq!(range_for(&["giff"]).unwrap(), None);
+    }
+
+    #[test]
+    fn last_compares_a_root_commit_with_the_empty_tree() {
+        assert_eq!(
+            range_for(&["giff", "--last"]).unwrap(),
+            Some(("HEAD~1".to_string(), "HEAD".to_string()))
+        );
+        assert_eq!(last_commit("root"), (empty_tree(), "root".to_string()));
+    }
+
+    fn positionals(cli: &[&str]) -> Result<Args, GiffError> {
+        crate::test_repo::init();
+        let mut args = Args::try_parse_from(cli).unwrap();
+        resolve_positionals(&mut args).map(|()| args)
+    }
+
+    #[test]
+    fn the_second_positional_is_a_revision_or_a_path() {
+        let args = positionals(&["giff", "root", "src"]).unwrap();
+        assert_eq!(args.branch, "root");
+        assert_eq!((args.range, args.paths), (None, vec!["src".to_string()]));
+
+        let args = positionals(&["giff", "root", "renamed", "--", "src"]).unwrap();
+        assert_eq!(
+            (args.range, args.paths),
+            (
+                Some(("root".to_string(), "renamed".to_string())),
+                vec!["src".to_string()]
+            )
+        );
+
+        assert!(matches!(
+            positionals(&["giff", "root", "no-such-thing"]),
+            Err(GiffError::Invalid(_))
+        ));
+        assert!(matches!(
+            positionals(&["giff", "root", "renamed", "--last"]),
+            Err(GiffError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn two_blobs_are_compared_with_each_other() {
+        crate::test_repo::init();
+        assert!(is_blob("HEAD:src/lib.rs"));
+        assert!(!is_blob("HEAD") && !is_blob("HEAD:src"));
+
+        let args = positionals(&["giff", "root:notes.txt", "HEAD:my notes.txt"]).unwrap();
+        assert_eq!(
+            args.range,
+            Some((
+                "root:notes.txt".to_string(),
+                "HEAD:my notes.txt".to_string()
+            ))
+        );
+        assert!(matches!(
+            positionals(&["giff", "HEAD:src/lib.rs", "root"]),
+            Err(GiffError::Invalid(_))
+        ));
+        assert!(matches!(
+            positionals(&["giff", "HEAD:src/lib.rs"]),
+            Err(GiffError::Invalid(_))
+        ));
+    }
 }
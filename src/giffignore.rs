@@ -0,0 +1,57 @@
+use crate::parser::FileChanges;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::Path;
+
+pub const FILE_NAME: &str = ".giffignore";
+
+/// Loads `.giffignore` from the repository root, if there is one.
+pub fn load(repo_root: &Path) -> Option<Gitignore> {
+    let path = repo_root.join(FILE_NAME);
+    if !path.is_file() {
+        return None;
+    }
+
+    let mut builder = GitignoreBuilder::new(repo_root);
+    if let Some(err) = builder.add(&path) {
+        eprintln!("Ignoring invalid {}: {}", FILE_NAME, err);
+        return None;
+    }
+    builder.build().ok()
+}
+
+/// Drops files matched by `ignore` and returns how many were hidden.
+pub fn apply(file_changes: &mut FileChanges, ignore: &Gitignore) -> usize {
+    let before = file_changes.len();
+    file_changes.retain(|path, _| !ignore.matched_path_or_any_parents(path, false).is_ignore());
+    before - file_changes.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::FileChange;
+
+    #[test]
+    fn matching_files_are_hidden_and_counted() {
+        let mut builder = GitignoreBuilder::new("/repo");
+        for line in ["*.lock", "generated/", "!keep.lock"] {
+            builder.add_line(None, line).unwrap();
+        }
+        let ignore = builder.build().unwrap();
+
+        let mut file_changes: FileChanges = [
+            "Cargo.lock",
+            "keep.lock",
+            "generated/schema.rs",
+            "src/main.rs",
+        ]
+        .into_iter()
+        .map(|path| (path.to_string(), FileChange::default()))
+        .collect();
+        assert_eq!(apply(&mut file_changes, &ignore), 2);
+        assert_eq!(
+            file_changes.keys().collect::<Vec<_>>(),
+            ["keep.lock", "src/main.rs"]
+        );
+    }
+}
@@ -1,46 +1,606 @@
 mod args;
+mod binary;
+mod cache;
+mod color;
+mod config;
+mod diff_options;
+mod error;
+mod export;
 mod giff;
+mod giffignore;
+mod moved;
+mod names;
 mod parser;
+mod patch_series;
+mod pick;
+mod progress;
+mod quickfix;
+mod range_diff;
+mod stage;
+mod stat;
+mod symbols;
 mod table;
+#[cfg(test)]
+mod test_repo;
+mod watch;
+mod whitespace;
 
-use args::Args;
-use clap::Parser;
+use args::{Args, LineFilter, View};
+use clap::{CommandFactory, FromArgMatches};
 use comfy_table::Cell;
 use comfy_table::{presets::UTF8_FULL_CONDENSED, Table};
 use crossterm::{
-    execute,
+    cursor, execute,
     terminal::{self, ClearType},
 };
-use std::io::{self};
+use giff::DiffOutput;
+use std::io::{self, IsTerminal};
+use std::path::Path;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Args::parse();
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    if let Some(path) = &args.git_path {
+        giff::set_git_path(path.clone());
+    }
+    if let Some(dir) = &args.directory {
+        giff::set_work_dir(dir.clone());
+    }
+    if let Some(path) = &args.worktree {
+        if let Err(err) = giff::check_worktree(path) {
+            exit_with_error(err);
+        }
+        giff::set_work_dir(path.clone());
+    }
+    if let Err(err) = config::apply_git_config(&mut args, &matches) {
+        exit_with_error(err);
+    }
+    color::set_background(args.background);
+    if let Some(refs) = &args.range_diff {
+        if let Err(err) = print_range_diff(&args, refs) {
+            exit_with_error(err);
+        }
+        return Ok(());
+    }
+    if let Some(dir) = &args.patch_dir {
+        if let Err(err) = print_patch_series(&args, dir) {
+            exit_with_error(err);
+        }
+        return Ok(());
+    }
+    if args.stage_hunks {
+        if let Err(err) = stage_hunks(&args) {
+            exit_with_error(err);
+        }
+        return Ok(());
+    }
+    if let Err(err) = giff::resolve_positionals(&mut args) {
+        exit_with_error(err);
+    }
+    if args.range.is_none() {
+        args.range = if args.pick {
+            match pick::pick_range() {
+                Ok(range) => Some(range),
+                Err(err) => exit_with_error(err),
+            }
+        } else {
+            giff::resolve_range(&args).unwrap_or_else(|err| exit_with_error(err))
+        };
+    }
+    if args.follow {
+        if let Err(err) = giff::follow_renames(&mut args) {
+            exit_with_error(err);
+        }
+    }
+    if args.inline {
+        args.view = View::Inline;
+    }
+
+    if args.verbose {
+        eprintln!("{}", giff::describe_command(&args));
+    }
+
+    if args.annotate_commits {
+        if let Err(err) = print_annotated_commits(&mut args) {
+            exit_with_error(err);
+        }
+        return Ok(());
+    }
 
     // Execute git diff command
-    let diff_output = giff::get_diff_output(&args.branch)?;
+    let spinner = if args.no_interactive {
+        progress::Spinner::disabled()
+    } else {
+        progress::Spinner::start("Running git diff…")
+    };
+    let diff_output = match giff::get_diff_output(&args) {
+        Ok(diff_output) => diff_output,
+        Err(err) => {
+            spinner.finish();
+            exit_with_error(err);
+        }
+    };
 
-    // Clear the terminal
-    let mut stdout = io::stdout();
-    execute!(stdout, terminal::Clear(ClearType::All))?;
+    match &diff_output {
+        DiffOutput::Empty if args.check_whitespace => spinner.finish(),
+        DiffOutput::Empty => {
+            spinner.finish();
+            print_diff(&args, &diff_output)?;
+        }
+        DiffOutput::Changes(diff) => {
+            // Parse and accumulate diff output
+            let file_changes = parser::parse_diff_output_with_progress(diff, |files| {
+                spinner.set_message(format!("Parsing {} files…", files))
+            });
+            spinner.finish();
 
-    // Parse and accumulate diff output
-    let file_changes = parser::parse_diff_output(&diff_output);
+            let unrecognized = parser::unrecognized_preview(diff, &file_changes);
+            if args.check_whitespace {
+                let mut file_changes = file_changes;
+                apply_giffignore(&args, &mut file_changes);
+                let found = check_whitespace(&file_changes);
+                warn_unrecognized(&args, unrecognized);
+                std::process::exit(i32::from(found));
+            }
+            if let Some(dir) = &args.output {
+                let mut file_changes = file_changes;
+                apply_giffignore(&args, &mut file_changes);
+                let written = export::write_patches(dir, &file_changes)?;
+                println!("Wrote {} patches to {}", written, dir.display());
+            } else {
+                print_file_changes(&args, file_changes)?;
+            }
+            warn_unrecognized(&args, unrecognized);
+        }
+    }
 
-    // Create and configure the table
+    if args.watch {
+        watch::watch(&args, diff_output, |output| print_diff(&args, output))?;
+    }
+
+    Ok(())
+}
+
+fn exit_with_error(err: impl std::fmt::Display) -> ! {
+    eprintln!("{}", err);
+    std::process::exit(1);
+}
+
+fn print_diff(args: &Args, diff_output: &DiffOutput) -> Result<(), Box<dyn std::error::Error>> {
+    match diff_output {
+        DiffOutput::Empty
+            if args.name_only || args.name_status || args.quickfix || args.symbols =>
+        {
+            Ok(())
+        }
+        DiffOutput::Empty => {
+            clear_screen(args)?;
+            match &args.staged {
+                Some(base) => println!("Nothing staged differs from {}", base),
+                None => println!("No changes"),
+            }
+            Ok(())
+        }
+        DiffOutput::Changes(diff) => {
+            let file_changes = parser::parse_diff_output(diff);
+            let unrecognized = parser::unrecognized_preview(diff, &file_changes);
+            print_file_changes(args, file_changes)?;
+            warn_unrecognized(args, unrecognized);
+            Ok(())
+        }
+    }
+}
+
+/// The header for a diff table in `view`, naming the two sides.
+fn set_diff_header(table: &mut Table, view: View, base_label: String, head_label: String) {
+    let header = match view {
+        View::SideBySide => vec!["File".to_string(), base_label, head_label],
+        View::Unified => vec![
+            "File".to_string(),
+            "Old".to_string(),
+            "New".to_string(),
+            format!("{} → {}", base_label, head_label),
+        ],
+        View::Inline => vec![format!("{} → {}", base_label, head_label)],
+    };
+    table.set_header(
+        header
+            .into_iter()
+            .map(|title| Cell::new(title).set_alignment(comfy_table::CellAlignment::Center)),
+    );
+}
+
+/// An empty table filling the terminal, or `--width` columns when given.
+fn new_table(args: &Args) -> Table {
     let mut table = Table::new();
     table.set_content_arrangement(comfy_table::ContentArrangement::DynamicFullWidth);
     table.load_preset(UTF8_FULL_CONDENSED);
-    table.set_header(vec![
-        Cell::new("File").set_alignment(comfy_table::CellAlignment::Center),
-        Cell::new(args.branch.as_str()).set_alignment(comfy_table::CellAlignment::Center),
-        Cell::new("HEAD").set_alignment(comfy_table::CellAlignment::Center),
-    ]);
+    if args.no_interactive {
+        table.force_no_tty();
+    }
+    if let Some(width) = args.width {
+        table.set_width(width);
+    }
+    table
+}
+
+/// Shows every patch of a series in one table, each under its subject.
+fn print_patch_series(args: &Args, dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let patches = patch_series::read_dir(dir)
+        .map_err(|err| format!("Could not read patches from {}: {}", dir.display(), err))?;
+    if patches.is_empty() {
+        println!("No patches in {}", dir.display());
+        return Ok(());
+    }
 
-    // Add rows to the table
-    table::populate_table(&mut table, file_changes);
+    clear_screen(args)?;
+    let mut table = new_table(args);
+    set_diff_header(
+        &mut table,
+        args.view,
+        "Before".to_string(),
+        "After".to_string(),
+    );
+    for patch in patches {
+        let subject = Cell::new(patch.subject)
+            .fg(color::adapt(comfy_table::Color::Magenta))
+            .add_attribute(comfy_table::Attribute::Bold);
+        table.add_row(table::note_row(subject, args.view, true));
+        table::populate_table(&mut table, parser::parse_diff_output(&patch.diff), args);
+    }
+    println!("{}", table.trim_fmt());
+    Ok(())
+}
+
+/// Shows the working tree's unstaged hunks one at a time and asks whether to
+/// stage each. The hunks chosen from a file are staged together once it's
+/// done, or when quitting part way through it.
+fn stage_hunks(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let diff = stage::worktree_diff(&args.pathspecs)?;
+    let files: Vec<_> = parser::parse_diff_output(&diff)
+        .into_iter()
+        .map(|(file, change)| {
+            let (header, hunks) = stage::split_hunks(&change.patch);
+            (change.display_path(&file), header, hunks)
+        })
+        .filter(|(_, _, hunks)| !hunks.is_empty())
+        .collect();
+    let total: usize = files.iter().map(|(_, _, hunks)| hunks.len()).sum();
+    if total == 0 {
+        println!("No unstaged changes");
+        return Ok(());
+    }
+
+    let mut staged = 0;
+    let mut quit = false;
+    for (path, header, hunks) in &files {
+        let mut chosen = Vec::new();
+        for (idx, hunk) in hunks.iter().enumerate() {
+            let progress = format!("{}: hunk {}/{}", path, idx + 1, hunks.len());
+            show_hunk(args, header, hunk, &progress)?;
+
+            match stage::ask(hunk.groups() > 1)? {
+                stage::Answer::Stage => {
+                    chosen.push(hunk.clone());
+                    staged += 1;
+                }
+                stage::Answer::Skip => {}
+                stage::Answer::Split => {
+                    let groups = hunk.groups();
+                    let mut keep = vec![false; groups];
+                    for group in 0..groups {
+                        let mut only = vec![false; groups];
+                        only[group] = true;
+                        let part = format!("{}, part {}/{}", progress, group + 1, groups);
+                        show_hunk(args, header, &hunk.select(&only), &part)?;
+                        match stage::ask(false)? {
+                            stage::Answer::Stage => keep[group] = true,
+                            stage::Answer::Quit => {
+                                quit = true;
+                                break;
+                            }
+                            _ => {}
+                        }
+                    }
+                    let selected = hunk.select(&keep);
+                    if selected.has_changes() {
+                        chosen.push(selected);
+                        staged += 1;
+                    }
+                    if quit {
+                        break;
+                    }
+                }
+                stage::Answer::Quit => {
+                    quit = true;
+                    break;
+                }
+            }
+        }
+        if !chosen.is_empty() {
+            stage::apply_cached(&stage::patch(header, &chosen))?;
+        }
+        if quit {
+            break;
+        }
+    }
+    println!("Staged {} of {} hunks", staged, total);
+    Ok(())
+}
+
+/// Clears the screen and shows `hunk` as a diff of the index against the
+/// working tree, with `progress` below it.
+fn show_hunk(
+    args: &Args,
+    header: &str,
+    hunk: &stage::Hunk,
+    progress: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let patch = hunk.display(header);
+    clear_screen(args)?;
+    let mut table = new_table(args);
+    set_diff_header(
+        &mut table,
+        args.view,
+        "Index".to_string(),
+        "Working tree".to_string(),
+    );
+    table::populate_table(&mut table, parser::parse_diff_output(&patch), args);
+    println!("{}", table.trim_fmt());
+    eprintln!("{}", progress);
+    Ok(())
+}
+
+/// Shows which commits of one version of a branch correspond to which in
+/// another, and how the modified ones changed.
+fn print_range_diff(args: &Args, refs: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let [base, old_tip, new_tip] = refs else {
+        unreachable!("clap requires three values");
+    };
+    let output = range_diff::run(base, old_tip, new_tip)?;
+    let mut entries = range_diff::parse(&output);
+    if let Some(len) = args.abbrev {
+        range_diff::abbreviate(&mut entries, len);
+    }
+    if entries.is_empty() {
+        println!("No commits to compare");
+        return Ok(());
+    }
+
+    clear_screen(args)?;
+    let mut table = new_table(args);
+    table.set_header(
+        [old_tip.as_str(), "", new_tip.as_str(), "Commit"]
+            .into_iter()
+            .map(|title| Cell::new(title).set_alignment(comfy_table::CellAlignment::Center)),
+    );
+    range_diff::populate_table(&mut table, &entries);
+    println!("{}", table.trim_fmt());
+    Ok(())
+}
+
+/// Prints each whitespace error as `file:line: problem` followed by the line
+/// with its whitespace made visible. Returns whether there were any.
+fn check_whitespace(file_changes: &parser::FileChanges) -> bool {
+    let errors = whitespace::check(file_changes, whitespace::Rules::from_git_config());
+    for error in &errors {
+        println!("{}:{}: {}", error.file, error.line_number, error.problem);
+        println!("+{}", table::visualize_whitespace(&error.line));
+    }
+    !errors.is_empty()
+}
+
+/// Shows what each commit of the range changed, oldest first, under a row
+/// with its subject, so it's clear which commit made which change.
+fn print_annotated_commits(args: &mut Args) -> Result<(), Box<dyn std::error::Error>> {
+    let (base, head) = giff::revisions(args);
+    let (base_label, head_label) = giff::labels(args);
+    let commits = pick::parse_oneline(&giff::range_commits(&base, &head, &args.paths));
+    if commits.is_empty() {
+        println!("No commits between {} and {}", base_label, head_label);
+        return Ok(());
+    }
+
+    clear_screen(args)?;
+    let mut table = new_table(args);
+    set_diff_header(&mut table, args.view, base_label, head_label);
+
+    let len = giff::abbrev_len(args);
+    for commit in commits {
+        let sha = giff::abbreviate(&commit.sha, len);
+        let subject = Cell::new(format!("{} {}", sha, commit.subject))
+            .fg(color::adapt(comfy_table::Color::Magenta))
+            .add_attribute(comfy_table::Attribute::Bold);
+        table.add_row(table::note_row(subject, args.view, true));
+
+        args.range = Some((giff::parent_or_empty_tree(&commit.sha), commit.sha));
+        match giff::get_diff_output(args)? {
+            DiffOutput::Empty => {
+                let note = Cell::new("(no changes)").fg(color::adapt(comfy_table::Color::DarkGrey));
+                table.add_row(table::note_row(note, args.view, false));
+            }
+            DiffOutput::Changes(diff) => {
+                let mut file_changes = parser::parse_diff_output(&diff);
+                apply_giffignore(args, &mut file_changes);
+                table::populate_table(&mut table, file_changes, args);
+            }
+        }
+    }
+    println!("{}", table.trim_fmt());
+    Ok(())
+}
+
+/// Drops files matched by `.giffignore` unless `--no-ignore` was given, and
+/// returns how many were hidden.
+fn apply_giffignore(args: &Args, file_changes: &mut parser::FileChanges) -> usize {
+    if args.no_ignore {
+        return 0;
+    }
+    match giff::repo_root().and_then(|root| giffignore::load(&root)) {
+        Some(ignore) => giffignore::apply(file_changes, &ignore),
+        None => 0,
+    }
+}
+
+/// Warns on stderr when git printed a diff but the parser found no files in
+/// it, rather than silently showing an empty table. Called after the table is
+/// printed so clearing the screen doesn't hide it.
+fn warn_unrecognized(args: &Args, unrecognized: Option<Vec<&str>>) {
+    let Some(preview) = unrecognized else {
+        return;
+    };
+
+    eprintln!("Warning: git produced output but giff did not recognise any files in it");
+    if args.verbose {
+        eprintln!("The output starts with:");
+        for line in preview {
+            eprintln!("    {}", line);
+        }
+    } else {
+        eprintln!("Run with --verbose to see the start of the output");
+    }
+}
+
+/// Clears the terminal before a redraw, unless `--no-interactive` was given.
+fn clear_screen(args: &Args) -> io::Result<()> {
+    if args.no_interactive {
+        return Ok(());
+    }
+    let mut stdout = io::stdout();
+    execute!(
+        stdout,
+        terminal::Clear(ClearType::All),
+        cursor::MoveTo(0, 0)
+    )
+}
+
+fn print_file_changes(
+    args: &Args,
+    mut file_changes: parser::FileChanges,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if args.quickfix {
+        apply_giffignore(args, &mut file_changes);
+        print!("{}", quickfix::entries(&file_changes));
+        return Ok(());
+    }
+    if args.symbols {
+        apply_giffignore(args, &mut file_changes);
+        print!("{}", symbols::entries(&file_changes));
+        return Ok(());
+    }
+    if args.name_only || args.name_status {
+        apply_giffignore(args, &mut file_changes);
+        let entries = names::entries(&file_changes, args.name_status, args.null_terminated);
+        print!("{}", entries);
+        return Ok(());
+    }
+    if args.compact_summary {
+        apply_giffignore(args, &mut file_changes);
+        let color = io::stdout().is_terminal() && !args.no_interactive;
+        print!("{}", stat::compact_summary(&file_changes, color));
+        return Ok(());
+    }
+
+    clear_screen(args)?;
+
+    if let Some(state) = giff::branch_state().filter(giff::BranchState::is_notable) {
+        println!("{}", state);
+    }
+
+    if !args.paths.is_empty() {
+        println!("Limited to {}", args.paths.join(", "));
+    }
+    match args.only {
+        Some(LineFilter::Added) => println!("Showing only added lines"),
+        Some(LineFilter::Removed) => println!("Showing only removed lines"),
+        None => {}
+    }
+
+    if args.ignore_blank_lines {
+        println!("Ignoring changes to blank lines");
+    }
+    if args.ignore_all_space {
+        println!("Ignoring whitespace");
+    }
+    for pattern in &args.ignore_matching_lines {
+        println!("Ignoring changes to lines matching /{}/", pattern);
+    }
+
+    let hidden = apply_giffignore(args, &mut file_changes);
+    if hidden > 0 {
+        let noun = if hidden == 1 { "file" } else { "files" };
+        println!("{} {} hidden by {}", hidden, noun, giffignore::FILE_NAME);
+    }
+
+    if let Some(number) = args.file {
+        let total = file_changes.len();
+        let Some(file) = file_changes.keys().nth(number.get() - 1).cloned() else {
+            let noun = if total == 1 { "file" } else { "files" };
+            exit_with_error(format!(
+                "No file {}: there are {} changed {}",
+                number, total, noun
+            ));
+        };
+        file_changes.retain(|path, _| *path == file);
+        println!("File {} of {}", number, total);
+    }
+
+    let conflicted: Vec<usize> = file_changes
+        .values()
+        .map(|change| change.conflicts())
+        .filter(|&conflicts| conflicts > 0)
+        .collect();
+    if !conflicted.is_empty() {
+        let conflicts: usize = conflicted.iter().sum();
+        println!(
+            "{} {} in {} {}",
+            conflicts,
+            if conflicts == 1 {
+                "conflict"
+            } else {
+                "conflicts"
+            },
+            conflicted.len(),
+            if conflicted.len() == 1 {
+                "file"
+            } else {
+                "files"
+            }
+        );
+    }
+
+    // Create and configure the table
+    let mut table = new_table(args);
+
+    if args.summary {
+        let mut header = vec!["#", "File", "Status", "Added", "Removed"];
+        if args.stat_bars {
+            // The bar is split over two columns so each half gets its colour.
+            header.extend(["Changes", ""]);
+        }
+        table.set_header(
+            header
+                .into_iter()
+                .map(|title| Cell::new(title).set_alignment(comfy_table::CellAlignment::Center)),
+        );
+        table::populate_summary_table(&mut table, &file_changes, args.stat_bars);
+    } else {
+        let (base_label, head_label) = giff::labels(args);
+        set_diff_header(&mut table, args.view, base_label, head_label);
+
+        // Add rows to the table
+        table::populate_table(&mut table, file_changes, args);
+    }
 
     // Print the table
     println!("{}", table.trim_fmt());
 
+    if args.legend {
+        let mut legend = new_table(args);
+        legend.set_header(["Key", "Meaning"]);
+        table::populate_legend(&mut legend);
+        println!("{}", legend.trim_fmt());
+    }
+
     Ok(())
 }
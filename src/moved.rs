@@ -0,0 +1,135 @@
+use crate::parser::{FileChange, LineChange, Origin};
+use std::collections::{HashMap, HashSet};
+
+/// Blocks shorter than this aren't treated as moved; short runs such as a
+/// closing brace match by coincidence too often.
+const MIN_MOVED_LINES: usize = 3;
+
+/// Line numbers of removed (base) and added (head) lines that belong to a
+/// block moved elsewhere in the file.
+#[derive(Debug, Default)]
+pub struct MovedLines {
+    pub base: HashSet<usize>,
+    pub head: HashSet<usize>,
+}
+
+/// Finds runs of at least [`MIN_MOVED_LINES`] consecutive lines that were
+/// removed in one place and added verbatim in another, like
+/// `git diff --color-moved`.
+pub fn detect_moved(change: &FileChange) -> MovedLines {
+    let removed = changed_lines(&change.base_lines, Origin::Removed);
+    let added = changed_lines(&change.head_lines, Origin::Added);
+
+    let mut added_by_content: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (idx, (_, content)) in added.iter().enumerate() {
+        added_by_content.entry(content).or_default().push(idx);
+    }
+
+    let mut moved = MovedLines::default();
+    for start in 0..removed.len() {
+        let Some(candidates) = added_by_content.get(removed[start].1) else {
+            continue;
+        };
+        for &added_start in candidates {
+            let len = block_len(&removed[start..], &added[added_start..]);
+            let block = &removed[start..start + len];
+            if len >= MIN_MOVED_LINES && block.iter().any(|(_, line)| !line.trim().is_empty()) {
+                moved.base.extend(block.iter().map(|(num, _)| *num));
+                moved.head.extend(
+                    added[added_start..added_start + len]
+                        .iter()
+                        .map(|(num, _)| *num),
+                );
+            }
+        }
+    }
+    moved
+}
+
+/// The changed lines on one side.
+fn changed_lines(lines: &[LineChange], origin: Origin) -> Vec<(usize, &str)> {
+    lines
+        .iter()
+        .filter(|(_, line_origin, _)| *line_origin == origin)
+        .map(|(num, _, content)| (*num, content.as_str()))
+        .collect()
+}
+
+/// How many lines match from the start of both slices while staying on
+/// consecutive line numbers, i.e. within one contiguous block on each side.
+fn block_len(removed: &[(usize, &str)], added: &[(usize, &str)]) -> usize {
+    removed
+        .iter()
+        .zip(added)
+        .enumerate()
+        .take_while(|(offset, ((base_num, base), (head_num, head)))| {
+            base == head && *base_num == removed[0].0 + offset && *head_num == added[0].0 + offset
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(origin: Origin, lines: &[(usize, &str)]) -> Vec<LineChange> {
+        lines
+            .iter()
+            .map(|(num, content)| (*num, origin, content.to_string()))
+            .collect()
+    }
+
+    fn change(removed: &[(usize, &str)], added: &[(usize, &str)]) -> FileChange {
+        FileChange {
+            base_lines: lines(Origin::Removed, removed),
+            head_lines: lines(Origin::Added, added),
+            ..Default::default()
+        }
+    }
+
+    fn sorted(lines: &HashSet<usize>) -> Vec<usize> {
+        let mut lines: Vec<_> = lines.iter().copied().collect();
+        lines.sort();
+        lines
+    }
+
+    #[test]
+    fn a_three_line_block_is_moved() {
+        let change = change(
+            &[(2, "fn helper() {"), (3, "    42"), (4, "}")],
+            &[(10, "fn helper() {"), (11, "    42"), (12, "}")],
+        );
+        let moved = detect_moved(&change);
+        assert_eq!(sorted(&moved.base), [2, 3, 4]);
+        assert_eq!(sorted(&moved.head), [10, 11, 12]);
+    }
+
+    #[test]
+    fn blocks_shorter_than_the_minimum_are_not() {
+        let change = change(
+            &[(2, "    42"), (3, "}"), (7, "other")],
+            &[(10, "    42"), (11, "}"), (12, "changed")],
+        );
+        let moved = detect_moved(&change);
+        assert!(moved.base.is_empty() && moved.head.is_empty());
+    }
+
+    #[test]
+    fn blocks_must_stay_contiguous() {
+        // The same three lines, but not consecutive in the base.
+        let change = change(
+            &[(2, "a"), (3, "b"), (9, "c")],
+            &[(20, "a"), (21, "b"), (22, "c")],
+        );
+        assert!(detect_moved(&change).base.is_empty());
+    }
+
+    #[test]
+    fn all_blank_blocks_are_not_moved() {
+        let change = change(
+            &[(2, ""), (3, "  "), (4, "")],
+            &[(10, ""), (11, "  "), (12, "")],
+        );
+        assert!(detect_moved(&change).head.is_empty());
+    }
+}
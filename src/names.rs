@@ -0,0 +1,66 @@
+use crate::parser::FileChanges;
+
+/// Lists the changed files like `git diff --name-only`, or with
+/// `with_status` like `--name-status`: the status letter, the old path of a
+/// rename and the path, separated by tabs. With `null_terminated` (`-z`)
+/// every field ends in NUL instead, as git does, so paths may hold any
+/// character.
+pub fn entries(file_changes: &FileChanges, with_status: bool, null_terminated: bool) -> String {
+    let (separator, terminator) = if null_terminated {
+        ('\0', '\0')
+    } else {
+        ('\t', '\n')
+    };
+    let mut entries = String::new();
+    for (file, change) in file_changes {
+        if with_status {
+            entries.push(change.status.letter());
+            entries.push(separator);
+            if let Some(old_path) = &change.old_path {
+                entries.push_str(old_path);
+                entries.push(separator);
+            }
+        }
+        entries.push_str(file);
+        entries.push(terminator);
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_diff_output;
+
+    const DIFF: &str = "diff --git a/my file.txt b/my file.txt\n\
+                        index 1111111..2222222 100644\n\
+                        --- a/my file.txt\t\n\
+                        +++ b/my file.txt\t\n\
+                        @@ -1 +1 @@\n\
+                        -a\n\
+                        +b\n\
+                        diff --git a/old b/new\n\
+                        similarity index 90%\n\
+                        rename from old\n\
+                        rename to new\n";
+
+    #[test]
+    fn null_terminated_names_keep_spaces() {
+        let changes = parse_diff_output(DIFF);
+        assert_eq!(entries(&changes, false, true), "my file.txt\0new\0");
+        assert_eq!(entries(&changes, false, false), "my file.txt\nnew\n");
+    }
+
+    #[test]
+    fn null_terminated_status_separates_every_field() {
+        let changes = parse_diff_output(DIFF);
+        assert_eq!(
+            entries(&changes, true, true),
+            "M\0my file.txt\0R\0old\0new\0"
+        );
+        assert_eq!(
+            entries(&changes, true, false),
+            "M\tmy file.txt\nR\told\tnew\n"
+        );
+    }
+}
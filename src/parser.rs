@@ -1,65 +1,394 @@
 use regex::Regex;
-use std::collections::HashMap;
-
-pub fn parse_diff_output(
-    diff_output: &str,
-) -> HashMap<String, (Vec<(usize, String)>, Vec<(usize, String)>)> {
-    let diff_file_regex = Regex::new(r"^diff --git a/(.+) b/(.+)$").unwrap();
-    let hunk_header_regex = Regex::new(r"^@@ -(\d+),\d+ \+(\d+),\d+ @@").unwrap();
-    let mut file_changes: HashMap<String, (Vec<(usize, String)>, Vec<(usize, String)>)> =
-        HashMap::new();
+use std::collections::BTreeMap;
+
+/// A single diff line: its line number on that side, whether it was added,
+/// removed or is context, and its content without the `+`/`-`/` ` marker.
+pub type LineChange = (usize, Origin, String);
+
+/// Which side of the diff a line comes from, as its marker column says.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    Context,
+    Added,
+    Removed,
+}
+
+impl Origin {
+    /// The marker git writes before the line: ` `, `+` or `-`.
+    pub fn marker(self) -> char {
+        match self {
+            Origin::Context => ' ',
+            Origin::Added => '+',
+            Origin::Removed => '-',
+        }
+    }
+}
+
+/// Changed files keyed by path, in path order.
+pub type FileChanges = BTreeMap<String, FileChange>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FileStatus {
+    Added,
+    Deleted,
+    Renamed,
+    #[default]
+    Modified,
+}
+
+impl FileStatus {
+    /// The single-letter code `git diff --name-status` uses.
+    pub fn letter(self) -> char {
+        match self {
+            FileStatus::Added => 'A',
+            FileStatus::Deleted => 'D',
+            FileStatus::Renamed => 'R',
+            FileStatus::Modified => 'M',
+        }
+    }
+}
+
+/// Commits a submodule moved between; `None` when it was added or removed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubmoduleChange {
+    pub old_commit: Option<String>,
+    pub new_commit: Option<String>,
+}
+
+/// How a file's lines are terminated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+}
+
+impl std::fmt::Display for LineEnding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            LineEnding::Lf => "LF",
+            LineEnding::Crlf => "CRLF",
+        })
+    }
+}
+
+/// Everything parsed for one file of the diff.
+#[derive(Debug, Clone, Default)]
+pub struct FileChange {
+    pub status: FileStatus,
+    /// The path before a rename; the file is keyed by its new path.
+    pub old_path: Option<String>,
+    pub base_lines: Vec<LineChange>,
+    pub head_lines: Vec<LineChange>,
+    pub submodule: Option<SubmoduleChange>,
+    /// Git reported the file as binary, so it has no lines.
+    pub binary: bool,
+    /// Abbreviated blob ids from the `index` line; all zeros on the side
+    /// where the file doesn't exist.
+    pub blobs: Option<(String, String)>,
+    /// The base and head line numbers each hunk starts at.
+    pub hunks: Vec<(usize, usize)>,
+    /// File modes (e.g. `100755`) before and after, when git reports them:
+    /// on the `index` line, and for added and deleted files and mode changes.
+    pub old_mode: Option<String>,
+    pub new_mode: Option<String>,
+    /// Whether the last base and head lines lack a final newline, which git
+    /// marks by following the line with `\ No newline at end of file`.
+    pub no_newline: (bool, bool),
+    /// Set when the only change is converting line endings, from and to.
+    pub line_endings: Option<(LineEnding, LineEnding)>,
+    /// The file's section of the diff verbatim, from its `diff --git` line on.
+    pub patch: String,
+}
+
+impl FileChange {
+    /// `path`, or `old → path` for a rename.
+    pub fn display_path(&self, path: &str) -> String {
+        match &self.old_path {
+            Some(old_path) => format!("{} → {}", old_path, path),
+            None => path.to_string(),
+        }
+    }
+
+    pub fn additions(&self) -> usize {
+        self.head_lines
+            .iter()
+            .filter(|(_, origin, _)| *origin == Origin::Added)
+            .count()
+    }
+
+    /// How many conflict regions (`<<<<<<<` markers) the head side contains.
+    pub fn conflicts(&self) -> usize {
+        self.head_lines
+            .iter()
+            .filter(|(_, _, line)| conflict_marker(line) == Some(ConflictMarker::Ours))
+            .count()
+    }
+
+    pub fn deletions(&self) -> usize {
+        self.base_lines
+            .iter()
+            .filter(|(_, origin, _)| *origin == Origin::Removed)
+            .count()
+    }
+
+    /// Which of [`hunks`](Self::hunks) line `num` of one side falls in.
+    pub fn hunk_of(&self, num: usize, side: Side) -> Option<usize> {
+        self.hunks.iter().rposition(|&(base, head)| match side {
+            Side::Base => base <= num,
+            Side::Head => head <= num,
+        })
+    }
+
+    /// For a symlink whose target changed, the targets before and after;
+    /// `None` on a side where there was no link.
+    pub fn symlink_targets(&self) -> Option<(Option<&str>, Option<&str>)> {
+        let is_link = |mode: &Option<String>| mode.as_deref() == Some(SYMLINK_MODE);
+        if !is_link(&self.old_mode) && !is_link(&self.new_mode) {
+            return None;
+        }
+        // A link's content is its target, on a line of its own.
+        fn target(lines: &[LineChange], changed: Origin) -> Option<&str> {
+            lines
+                .iter()
+                .find(|(_, origin, _)| *origin == changed)
+                .map(|(_, _, target)| target.as_str())
+        }
+        let old = target(&self.base_lines, Origin::Removed);
+        let new = target(&self.head_lines, Origin::Added);
+        (old.is_some() || new.is_some()).then_some((old, new))
+    }
+}
+
+/// One side of a diff: the base (old) or head (new) version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Base,
+    Head,
+}
+
+/// The mode git records for a symbolic link.
+const SYMLINK_MODE: &str = "120000";
+
+/// The lines git writes around a merge conflict.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictMarker {
+    /// `<<<<<<<`, opening our side
+    Ours,
+    /// `|||||||`, opening the merge base in diff3 style
+    Base,
+    /// `=======`, between the two sides
+    Separator,
+    /// `>>>>>>>`, closing their side
+    Theirs,
+}
+
+/// Recognises a conflict marker in a line's content.
+pub fn conflict_marker(content: &str) -> Option<ConflictMarker> {
+    let marker = |prefix: &str| {
+        content.starts_with(prefix)
+            && content[prefix.len()..]
+                .chars()
+                .next()
+                .is_none_or(char::is_whitespace)
+    };
+
+    if marker("<<<<<<<") {
+        Some(ConflictMarker::Ours)
+    } else if marker("|||||||") {
+        Some(ConflictMarker::Base)
+    } else if marker("=======") {
+        Some(ConflictMarker::Separator)
+    } else if marker(">>>>>>>") {
+        Some(ConflictMarker::Theirs)
+    } else {
+        None
+    }
+}
+
+pub fn parse_diff_output(diff_output: &str) -> FileChanges {
+    parse_diff_output_with_progress(diff_output, |_| {})
+}
+
+/// Like [`parse_diff_output`], calling `on_file` with the number of files
+/// parsed so far each time a file is completed.
+pub fn parse_diff_output_with_progress<F>(diff_output: &str, mut on_file: F) -> FileChanges
+where
+    F: FnMut(usize),
+{
+    let diff_file_regex = Regex::new(r"^diff --git (.+)$").unwrap();
+    let old_path_regex = Regex::new(r"^--- (.+)$").unwrap();
+    let new_path_regex = Regex::new(r"^\+\+\+ (.+)$").unwrap();
+    let rename_from_regex = Regex::new(r"^rename from (.+)$").unwrap();
+    let rename_to_regex = Regex::new(r"^rename to (.+)$").unwrap();
+    let hunk_header_regex = Regex::new(r"^@@ -(\d+)(?:,\d+)? \+(\d+)(?:,\d+)? @@").unwrap();
+    let index_regex = Regex::new(r"^index ([0-9a-f]+)\.\.([0-9a-f]+)(?: (\d+))?").unwrap();
+    let subproject_regex = Regex::new(r"^([-+])Subproject commit ([0-9a-f]+)").unwrap();
+    let mut file_changes: FileChanges = BTreeMap::new();
     let mut current_file = String::new();
-    let mut base_lines = Vec::new();
-    let mut head_lines = Vec::new();
+    let mut current = FileChange::default();
+    // The current file's `a/`-style prefixes, and whether its first hunk is
+    // still to come.
+    let mut prefixes = Prefixes::default();
+    let mut in_header = false;
     let mut base_line_number = 1;
     let mut head_line_number = 1;
+    // Changed lines that ended in `\r`, which is stripped from their content.
+    let mut crlf_lines = (0, 0);
+    // The origin of the hunk line just read, which a `\ No newline` marker
+    // applies to.
+    let mut last_origin = None;
 
     // Regex to remove ANSI escape codes
     let ansi_escape_regex = Regex::new(r"\x1b\[.*?m").unwrap();
 
-    for line in diff_output.lines() {
-        let trimmed_line = line.trim();
-        let trimmed_line = ansi_escape_regex.replace_all(trimmed_line, "");
+    for raw_line in diff_output.split_inclusive('\n') {
+        let raw_line = ansi_escape_regex.replace_all(raw_line, "");
+        let line = raw_line.strip_suffix('\n').unwrap_or(&raw_line);
+        // Leading and trailing whitespace is part of the content, so only the
+        // line ending is stripped.
+        let trimmed_line = line.strip_suffix('\r').unwrap_or(line);
 
-        if let Some(caps) = diff_file_regex.captures(trimmed_line.as_ref()) {
+        if let Some(caps) = diff_file_regex.captures(trimmed_line) {
+            let finished = std::mem::take(&mut current);
             if !current_file.is_empty() {
-                file_changes.insert(
-                    current_file.clone(),
-                    (base_lines.clone(), head_lines.clone()),
-                );
-                base_lines.clear();
-                head_lines.clear();
+                let finished = with_line_endings(finished, std::mem::take(&mut crlf_lines));
+                file_changes.insert(current_file.clone(), finished);
+                on_file(file_changes.len());
             }
-            current_file = caps.get(1).unwrap().as_str().to_string();
+            current.patch.push_str(&raw_line);
+            (current_file, prefixes) = header_path(&caps[1]);
+            in_header = true;
+            last_origin = None;
             base_line_number = 1;
             head_line_number = 1;
             continue;
         }
+        current.patch.push_str(&raw_line);
+
+        // The `---`/`+++` and `rename to` lines are authoritative for the path;
+        // the `diff --git` header is ambiguous for renames.
+        // Git terminates paths containing spaces with a tab on these lines.
+        let path_line = |regex: &Regex, prefix: &str| {
+            let caps = regex.captures(trimmed_line).filter(|_| in_header)?;
+            let path = unquote(caps[1].trim_end_matches('\t'));
+            let stripped = path.strip_prefix(prefix).filter(|_| path != "/dev/null")?;
+            Some(stripped.to_string())
+        };
+        if let Some(path) = path_line(&old_path_regex, &prefixes.old) {
+            current_file = path;
+        } else if let Some(path) = path_line(&new_path_regex, &prefixes.new) {
+            current_file = path;
+        } else if let Some(caps) = rename_from_regex.captures(trimmed_line) {
+            current.old_path = Some(unquote(&caps[1]));
+            continue;
+        } else if let Some(caps) = rename_to_regex.captures(trimmed_line) {
+            current_file = unquote(&caps[1]);
+            current.status = FileStatus::Renamed;
+            continue;
+        }
 
-        if let Some(caps) = hunk_header_regex.captures(trimmed_line.as_ref()) {
+        if let Some(mode) = trimmed_line.strip_prefix("new file mode ") {
+            current.status = FileStatus::Added;
+            current.new_mode = Some(mode.to_string());
+            continue;
+        }
+        if let Some(mode) = trimmed_line.strip_prefix("deleted file mode ") {
+            current.status = FileStatus::Deleted;
+            current.old_mode = Some(mode.to_string());
+            continue;
+        }
+        if let Some(mode) = trimmed_line.strip_prefix("old mode ") {
+            current.old_mode = Some(mode.to_string());
+            continue;
+        }
+        if let Some(mode) = trimmed_line.strip_prefix("new mode ") {
+            current.new_mode = Some(mode.to_string());
+            continue;
+        }
+
+        if let Some(caps) = index_regex.captures(trimmed_line) {
+            current.blobs = Some((caps[1].to_string(), caps[2].to_string()));
+            // The mode is only given here when it's the same on both sides.
+            if let Some(mode) = caps.get(3) {
+                current.old_mode = Some(mode.as_str().to_string());
+                current.new_mode = Some(mode.as_str().to_string());
+            }
+            continue;
+        }
+        // `git format-patch` and `--binary` write the contents as a
+        // `GIT binary patch` instead; its `literal`/`delta` payload comes
+        // before any hunk, so it's skipped below as metadata.
+        let binary_files =
+            trimmed_line.starts_with("Binary files ") && trimmed_line.ends_with(" differ");
+        if binary_files || trimmed_line == "GIT binary patch" {
+            current.binary = true;
+            continue;
+        }
+
+        if let Some(caps) = hunk_header_regex.captures(trimmed_line) {
             base_line_number = caps.get(1).unwrap().as_str().parse::<usize>().unwrap();
             head_line_number = caps.get(2).unwrap().as_str().parse::<usize>().unwrap();
+            current.hunks.push((base_line_number, head_line_number));
+            in_header = false;
             continue;
         }
 
-        if trimmed_line.starts_with("index")
-            || trimmed_line.starts_with("---")
-            || trimmed_line.starts_with("+++")
-            || trimmed_line.starts_with("@@")
-            || trimmed_line.starts_with("new")
-        {
+        // Everything else before the first hunk is metadata, such as the
+        // `---`/`+++` lines or `similarity index`. In a hunk, a line like
+        // `----` is a removed `---`.
+        if in_header {
+            continue;
+        }
+
+        if let Some(caps) = subproject_regex.captures(trimmed_line) {
+            let submodule = current.submodule.get_or_insert_with(Default::default);
+            let commit = Some(caps[2].to_string());
+            if &caps[1] == "-" {
+                submodule.old_commit = commit;
+            } else {
+                submodule.new_commit = commit;
+            }
+            continue;
+        }
+
+        if trimmed_line.starts_with('\\') {
+            // `\ No newline at end of file` describes the line before it and
+            // isn't a line of the file itself.
+            match last_origin {
+                Some(Origin::Removed) => current.no_newline.0 = true,
+                Some(Origin::Added) => current.no_newline.1 = true,
+                Some(Origin::Context) => current.no_newline = (true, true),
+                None => {}
+            }
             continue;
         }
 
-        if trimmed_line.starts_with('-') {
-            base_lines.push((base_line_number, trimmed_line.to_string()));
+        let is_crlf = trimmed_line.len() < line.len();
+        if let Some(content) = trimmed_line.strip_prefix('-') {
+            last_origin = Some(Origin::Removed);
+            crlf_lines.0 += usize::from(is_crlf);
+            current
+                .base_lines
+                .push((base_line_number, Origin::Removed, content.to_string()));
             base_line_number += 1;
-        } else if trimmed_line.starts_with('+') {
-            head_lines.push((head_line_number, trimmed_line.to_string()));
+        } else if let Some(content) = trimmed_line.strip_prefix('+') {
+            last_origin = Some(Origin::Added);
+            crlf_lines.1 += usize::from(is_crlf);
+            current
+                .head_lines
+                .push((head_line_number, Origin::Added, content.to_string()));
             head_line_number += 1;
         } else {
-            base_lines.push((base_line_number, trimmed_line.to_string()));
-            head_lines.push((head_line_number, trimmed_line.to_string()));
+            // A context line, which may have lost its ` ` marker to an
+            // editor stripping trailing whitespace.
+            last_origin = Some(Origin::Context);
+            let content = trimmed_line.strip_prefix(' ').unwrap_or(trimmed_line);
+            current
+                .base_lines
+                .push((base_line_number, Origin::Context, content.to_string()));
+            current
+                .head_lines
+                .push((head_line_number, Origin::Context, content.to_string()));
             base_line_number += 1;
             head_line_number += 1;
         }
@@ -67,8 +396,479 @@ pub fn parse_diff_output(
 
     // Insert last file changes
     if !current_file.is_empty() {
-        file_changes.insert(current_file, (base_lines, head_lines));
+        file_changes.insert(current_file, with_line_endings(current, crlf_lines));
+        on_file(file_changes.len());
     }
 
     file_changes
 }
+
+/// Sets [`FileChange::line_endings`] when every removed line was re-added
+/// unchanged apart from its line ending. `crlf_lines` counts the removed and
+/// added lines that ended in `\r`.
+fn with_line_endings(mut change: FileChange, crlf_lines: (usize, usize)) -> FileChange {
+    let removed: Vec<&str> = change
+        .base_lines
+        .iter()
+        .filter(|(_, origin, _)| *origin == Origin::Removed)
+        .map(|(_, _, line)| line.as_str())
+        .collect();
+    let added: Vec<&str> = change
+        .head_lines
+        .iter()
+        .filter(|(_, origin, _)| *origin == Origin::Added)
+        .map(|(_, _, line)| line.as_str())
+        .collect();
+    if removed.is_empty() || removed != added {
+        return change;
+    }
+
+    change.line_endings = match crlf_lines {
+        (crlf, 0) if crlf == removed.len() => Some((LineEnding::Crlf, LineEnding::Lf)),
+        (0, crlf) if crlf == added.len() => Some((LineEnding::Lf, LineEnding::Crlf)),
+        _ => None,
+    };
+    change
+}
+
+/// When `diff_output` has content but no files were recognised in it, returns
+/// its first few lines so the caller can show what the parser choked on.
+pub fn unrecognized_preview<'a>(
+    diff_output: &'a str,
+    file_changes: &FileChanges,
+) -> Option<Vec<&'a str>> {
+    const PREVIEW_LINES: usize = 5;
+
+    if !file_changes.is_empty() || diff_output.trim().is_empty() {
+        return None;
+    }
+    Some(diff_output.lines().take(PREVIEW_LINES).collect())
+}
+
+/// What the old and new paths of a file's diff start with: `a/` and `b/` by
+/// default, but whatever `--src-prefix`/`--dst-prefix` or `diff.mnemonicPrefix`
+/// chose, or nothing with `--no-prefix`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Prefixes {
+    old: String,
+    new: String,
+}
+
+impl Prefixes {
+    /// Neither side has a prefix, as with `--no-prefix`.
+    fn no_prefix() -> Self {
+        Prefixes {
+            old: String::new(),
+            new: String::new(),
+        }
+    }
+}
+
+impl Default for Prefixes {
+    fn default() -> Self {
+        Prefixes {
+            old: "a/".to_string(),
+            new: "b/".to_string(),
+        }
+    }
+}
+
+/// Picks the path and prefixes from the `<old> <new>` of a `diff --git`
+/// header.
+///
+/// Paths may contain spaces, so the split point isn't known. For an unrenamed
+/// file both halves name the same path, either exactly (no prefix) or after
+/// their first component, so prefer a split where they match. Failing that
+/// (a rename), assume git's default prefixes; the `rename to` line then
+/// gives the real path. Quoted paths are unambiguous and split where the
+/// quotes say.
+fn header_path(paths: &str) -> (String, Prefixes) {
+    if let Some((old, new)) = split_quoted(paths) {
+        if old == new {
+            return (new, Prefixes::no_prefix());
+        }
+        return same_path(&old, &new).unwrap_or_else(|| {
+            let path = new.strip_prefix("b/").unwrap_or(&new);
+            (path.to_string(), Prefixes::default())
+        });
+    }
+
+    let splits = || {
+        paths
+            .match_indices(' ')
+            .map(|(idx, _)| (&paths[..idx], &paths[idx + 1..]))
+    };
+    if let Some((_, new)) = splits().find(|(old, new)| old == new) {
+        return (new.to_string(), Prefixes::no_prefix());
+    }
+    if let Some(found) = splits().find_map(|(old, new)| same_path(old, new)) {
+        return found;
+    }
+    let path = match paths.split_once(" b/") {
+        Some((_, new)) => new,
+        None => paths.rsplit(' ').next().unwrap_or(paths),
+    };
+    (path.to_string(), Prefixes::default())
+}
+
+/// The path and prefixes when `old` and `new` name the same path after their
+/// first component, e.g. `a/src/main.rs` and `b/src/main.rs`.
+fn same_path(old: &str, new: &str) -> Option<(String, Prefixes)> {
+    let (old_prefix, old_path) = old.split_once('/')?;
+    let (new_prefix, new_path) = new.split_once('/')?;
+    if old_path != new_path || new_path.is_empty() {
+        return None;
+    }
+    let prefixes = Prefixes {
+        old: format!("{}/", old_prefix),
+        new: format!("{}/", new_prefix),
+    };
+    Some((new_path.to_string(), prefixes))
+}
+
+/// Splits a `diff --git` header's `<old> <new>` when git quoted either of
+/// them, returning both unquoted.
+fn split_quoted(paths: &str) -> Option<(String, String)> {
+    if let Some((old, rest)) = quoted(paths) {
+        let new = rest.strip_prefix(' ')?;
+        return Some((old, unquote(new)));
+    }
+    // Only the new path is quoted: it's the quoted string running to the end.
+    paths
+        .match_indices(" \"")
+        .find_map(|(idx, _)| match quoted(&paths[idx + 1..]) {
+            Some((new, "")) => Some((paths[..idx].to_string(), new)),
+            _ => None,
+        })
+}
+
+/// A path as git writes it in diff headers: as is, or when it holds unusual
+/// characters, in double quotes with C-style escapes, e.g. `"caf\303\251"` for
+/// `café`.
+fn unquote(path: &str) -> String {
+    match quoted(path) {
+        Some((unquoted, "")) => unquoted,
+        _ => path.to_string(),
+    }
+}
+
+/// Decodes the quoted string `text` starts with, returning it and whatever
+/// follows the closing quote.
+fn quoted(text: &str) -> Option<(String, &str)> {
+    let body = text.strip_prefix('"')?.as_bytes();
+    let mut bytes = Vec::new();
+    let mut idx = 0;
+    while idx < body.len() {
+        match body[idx] {
+            b'"' => {
+                return Some((
+                    String::from_utf8_lossy(&bytes).into_owned(),
+                    &text[idx + 2..],
+                ))
+            }
+            b'\\' => {
+                let escaped = *body.get(idx + 1)?;
+                let byte = match escaped {
+                    b'a' => 0x07,
+                    b'b' => 0x08,
+                    b't' => b'\t',
+                    b'n' => b'\n',
+                    b'v' => 0x0b,
+                    b'f' => 0x0c,
+                    b'r' => b'\r',
+                    // Bytes outside printable ASCII, as three octal digits.
+                    b'0'..=b'7' => {
+                        let digits = std::str::from_utf8(body.get(idx + 1..idx + 4)?).ok()?;
+                        idx += 2;
+                        u8::from_str_radix(digits, 8).ok()?
+                    }
+                    other => other,
+                };
+                bytes.push(byte);
+                idx += 2;
+            }
+            byte => {
+                bytes.push(byte);
+                idx += 1;
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoted_paths_are_decoded() {
+        let diff = "diff --git \"a/caf\\303\\251\" \"b/caf\\303\\251\"\n\
+                    index 1111111..2222222 100644\n\
+                    --- \"a/caf\\303\\251\"\n\
+                    +++ \"b/caf\\303\\251\"\n\
+                    @@ -1 +1 @@\n\
+                    -a\n\
+                    +b\n";
+        let changes = parse_diff_output(diff);
+        assert_eq!(changes.keys().collect::<Vec<_>>(), ["café"]);
+    }
+
+    #[test]
+    fn quoted_renames_keep_escaped_characters() {
+        let diff = "diff --git a/old \"b/new\\nline\"\n\
+                    similarity index 100%\n\
+                    rename from old\n\
+                    rename to \"new\\nline\"\n";
+        let changes = parse_diff_output(diff);
+        let change = &changes["new\nline"];
+        assert_eq!(change.status, FileStatus::Renamed);
+        assert_eq!(change.old_path.as_deref(), Some("old"));
+    }
+
+    #[test]
+    fn no_newline_marker_flags_the_line_before_it() {
+        let diff = "diff --git a/f b/f\n\
+                    --- a/f\n\
+                    +++ b/f\n\
+                    @@ -1,2 +1,2 @@\n\
+                    \x20a\n\
+                    -b\n\
+                    \\ No newline at end of file\n\
+                    +c\n";
+        let change = &parse_diff_output(diff)["f"];
+        assert_eq!(change.no_newline, (true, false));
+        assert_eq!(
+            change.head_lines,
+            [
+                (1, Origin::Context, "a".to_string()),
+                (2, Origin::Added, "c".to_string())
+            ]
+        );
+        assert_eq!(
+            change.base_lines.last(),
+            Some(&(2, Origin::Removed, "b".to_string()))
+        );
+    }
+
+    #[test]
+    fn unquote_leaves_plain_paths_alone() {
+        assert_eq!(unquote("src/main.rs"), "src/main.rs");
+        assert_eq!(
+            unquote("\"tab\\there \\\"q\\\" \\\\\""),
+            "tab\there \"q\" \\"
+        );
+    }
+
+    #[test]
+    fn paths_containing_the_new_prefix_split_where_both_sides_match() {
+        assert_eq!(
+            header_path("a/x b/y b/x b/y"),
+            ("x b/y".to_string(), Prefixes::default())
+        );
+        let diff = "diff --git a/docs b/notes.md b/docs b/notes.md\n\
+                    --- a/docs b/notes.md\n\
+                    +++ b/docs b/notes.md\n\
+                    @@ -1 +1 @@\n\
+                    -a\n\
+                    +b\n";
+        let changes = parse_diff_output(diff);
+        assert_eq!(changes.keys().collect::<Vec<_>>(), ["docs b/notes.md"]);
+        assert_eq!(changes["docs b/notes.md"].additions(), 1);
+    }
+
+    #[test]
+    fn renamed_paths_with_spaces_come_from_the_rename_lines() {
+        let diff = "diff --git a/old name b/new name\n\
+                    similarity index 100%\n\
+                    rename from old name\n\
+                    rename to new name\n";
+        let changes = parse_diff_output(diff);
+        let change = &changes["new name"];
+        assert_eq!(change.status, FileStatus::Renamed);
+        assert_eq!(change.old_path.as_deref(), Some("old name"));
+    }
+
+    fn single_line_change(old: &str, new: &str) -> String {
+        format!(
+            "diff --git {old} {new}\n\
+             --- {old}\n\
+             +++ {new}\n\
+             @@ -1 +1 @@\n\
+             -a\n\
+             +b\n"
+        )
+    }
+
+    #[test]
+    fn no_prefix_diffs_keep_the_whole_path() {
+        assert_eq!(
+            header_path("src/main.rs src/main.rs"),
+            ("src/main.rs".to_string(), Prefixes::no_prefix())
+        );
+        let changes = parse_diff_output(&single_line_change("src/main.rs", "src/main.rs"));
+        assert_eq!(changes.keys().collect::<Vec<_>>(), ["src/main.rs"]);
+        assert_eq!(changes["src/main.rs"].additions(), 1);
+    }
+
+    #[test]
+    fn mnemonic_prefixes_are_stripped() {
+        let (path, prefixes) = header_path("i/src/main.rs w/src/main.rs");
+        assert_eq!(path, "src/main.rs");
+        assert_eq!((prefixes.old.as_str(), prefixes.new.as_str()), ("i/", "w/"));
+        let changes = parse_diff_output(&single_line_change("c/f", "w/f"));
+        assert_eq!(changes.keys().collect::<Vec<_>>(), ["f"]);
+        assert_eq!(changes["f"].additions(), 1);
+    }
+
+    #[test]
+    fn custom_prefixes_are_stripped() {
+        let changes = parse_diff_output(&single_line_change("old/lib.rs", "new/lib.rs"));
+        assert_eq!(changes.keys().collect::<Vec<_>>(), ["lib.rs"]);
+        assert_eq!(changes["lib.rs"].status, FileStatus::Modified);
+    }
+
+    #[test]
+    fn dash_and_plus_lines_inside_a_hunk_are_content() {
+        let diff = "diff --git a/notes.md b/notes.md\n\
+                    --- a/notes.md\n\
+                    +++ b/notes.md\n\
+                    @@ -1,3 +1,3 @@\n\
+                    \x20title\n\
+                    ----\n\
+                    ++++ x\n\
+                    \x20end\n";
+        let changes = parse_diff_output(diff);
+        assert_eq!(changes.keys().collect::<Vec<_>>(), ["notes.md"]);
+        let change = &changes["notes.md"];
+        assert_eq!(
+            change.base_lines[1],
+            (2, Origin::Removed, "---".to_string())
+        );
+        assert_eq!(
+            change.head_lines[1],
+            (2, Origin::Added, "+++ x".to_string())
+        );
+        assert_eq!((change.base_lines.len(), change.head_lines.len()), (3, 3));
+    }
+
+    #[test]
+    fn git_binary_patches_are_binary() {
+        let diff = "diff --git a/img.png b/img.png\n\
+                    new file mode 100644\n\
+                    index 0000000000000000000000000000000000000000..2e3d1dab38298ec772afa87ad75a19d79755aa5d\n\
+                    GIT binary patch\n\
+                    literal 26\n\
+                    ecmeAS@N?(olHy`uVBq!ia0vp^OhC+BQ2_ur*#px6\n\
+                    \n\
+                    literal 0\n\
+                    HcmV?d00001\n\
+                    \n";
+        let change = &parse_diff_output(diff)["img.png"];
+        assert!(change.binary);
+        assert_eq!(change.status, FileStatus::Added);
+        assert!(change.base_lines.is_empty() && change.head_lines.is_empty());
+    }
+
+    #[test]
+    fn a_change_of_only_line_endings_is_recognised() {
+        let diff = "diff --git a/f b/f\n\
+                    --- a/f\n\
+                    +++ b/f\n\
+                    @@ -1,2 +1,2 @@\n\
+                    -one\r\n\
+                    -two\r\n\
+                    +one\n\
+                    +two\n";
+        let change = &parse_diff_output(diff)["f"];
+        assert_eq!(
+            change.line_endings,
+            Some((LineEnding::Crlf, LineEnding::Lf))
+        );
+
+        let edited = diff.replace("+two\n", "+TWO\n");
+        assert_eq!(parse_diff_output(&edited)["f"].line_endings, None);
+    }
+
+    #[test]
+    fn symlink_targets_come_from_the_changed_lines() {
+        let diff = "diff --git a/link b/link\n\
+                    index 1de5659..8ad9fc1 120000\n\
+                    --- a/link\n\
+                    +++ b/link\n\
+                    @@ -1 +1 @@\n\
+                    -old/target\n\
+                    \\ No newline at end of file\n\
+                    +new/target\n\
+                    \\ No newline at end of file\n";
+        let change = &parse_diff_output(diff)["link"];
+        assert_eq!(
+            change.symlink_targets(),
+            Some((Some("old/target"), Some("new/target")))
+        );
+
+        let regular = diff.replace(" 120000", " 100644");
+        assert_eq!(parse_diff_output(&regular)["link"].symlink_targets(), None);
+    }
+
+    #[test]
+    fn submodule_commits_are_recorded() {
+        let diff = "diff --git a/vendor/lib b/vendor/lib\n\
+                    index 3f1c2a9..9b0e7d4 160000\n\
+                    --- a/vendor/lib\n\
+                    +++ b/vendor/lib\n\
+                    @@ -1 +1 @@\n\
+                    -Subproject commit 3f1c2a9e8d7b6a5f4e3d2c1b0a9f8e7d6c5b4a39\n\
+                    +Subproject commit 9b0e7d4c3b2a1f0e9d8c7b6a5f4e3d2c1b0a9f84\n";
+        let change = &parse_diff_output(diff)["vendor/lib"];
+        assert_eq!(
+            change.submodule,
+            Some(SubmoduleChange {
+                old_commit: Some("3f1c2a9e8d7b6a5f4e3d2c1b0a9f8e7d6c5b4a39".to_string()),
+                new_commit: Some("9b0e7d4c3b2a1f0e9d8c7b6a5f4e3d2c1b0a9f84".to_string()),
+            })
+        );
+
+        let added = "diff --git a/vendor/lib b/vendor/lib\n\
+                     new file mode 160000\n\
+                     index 0000000..9b0e7d4\n\
+                     --- /dev/null\n\
+                     +++ b/vendor/lib\n\
+                     @@ -0,0 +1 @@\n\
+                     +Subproject commit 9b0e7d4c3b2a1f0e9d8c7b6a5f4e3d2c1b0a9f84\n";
+        assert_eq!(
+            parse_diff_output(added)["vendor/lib"].submodule,
+            Some(SubmoduleChange {
+                old_commit: None,
+                new_commit: Some("9b0e7d4c3b2a1f0e9d8c7b6a5f4e3d2c1b0a9f84".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn edits_to_a_renamed_file_attach_to_the_new_path() {
+        let diff = "diff --git a/src/old.rs b/src/new.rs\n\
+                    similarity index 80%\n\
+                    rename from src/old.rs\n\
+                    rename to src/new.rs\n\
+                    index 1111111..2222222 100644\n\
+                    --- a/src/old.rs\n\
+                    +++ b/src/new.rs\n\
+                    @@ -1,2 +1,2 @@\n\
+                    \x20fn main() {}\n\
+                    -fn old() {}\n\
+                    +fn new() {}\n";
+        let changes = parse_diff_output(diff);
+        assert_eq!(changes.keys().collect::<Vec<_>>(), ["src/new.rs"]);
+        let change = &changes["src/new.rs"];
+        assert_eq!(change.status, FileStatus::Renamed);
+        assert_eq!(change.old_path.as_deref(), Some("src/old.rs"));
+        assert_eq!((change.deletions(), change.additions()), (1, 1));
+        assert_eq!(
+            change.head_lines[1],
+            (2, Origin::Added, "fn new() {}".to_string())
+        );
+        assert!(change
+            .patch
+            .starts_with("diff --git a/src/old.rs b/src/new.rs\n"));
+    }
+}
@@ -0,0 +1,180 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// One patch of a `git format-patch` series.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Patch {
+    /// The `Subject:` header, e.g. `[PATCH 1/2] Fix the parser`.
+    pub subject: String,
+    /// The `diff --git` sections, without the mail headers, diffstat or
+    /// signature.
+    pub diff: String,
+}
+
+/// Reads the `*.patch` and `*.mbox` files in `dir`, in name order, which is
+/// the order `git format-patch` numbers them in. A file may hold several
+/// messages, as an mbox does.
+pub fn read_dir(dir: &Path) -> io::Result<Vec<Patch>> {
+    let mut files: Vec<_> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    files.retain(|path| {
+        path.is_file()
+            && path
+                .extension()
+                .is_some_and(|ext| ext == "patch" || ext == "mbox")
+    });
+    files.sort();
+
+    let mut patches = Vec::new();
+    for file in files {
+        let text = fs::read_to_string(&file)?;
+        for message in split_mbox(&text) {
+            let mut patch = parse(message);
+            if patch.subject.is_empty() {
+                patch.subject = file.display().to_string();
+            }
+            patches.push(patch);
+        }
+    }
+    Ok(patches)
+}
+
+/// Splits an mbox into messages at each `From ` line that starts a message:
+/// the first line, or one following a blank line.
+fn split_mbox(text: &str) -> Vec<&str> {
+    let mut starts = Vec::new();
+    let mut offset = 0;
+    let mut after_blank = true;
+    for line in text.split_inclusive('\n') {
+        if after_blank && line.starts_with("From ") {
+            starts.push(offset);
+        }
+        after_blank = line.trim_end().is_empty();
+        offset += line.len();
+    }
+    if starts.first() != Some(&0) {
+        starts.insert(0, 0);
+    }
+
+    starts
+        .iter()
+        .zip(starts.iter().skip(1).chain([&text.len()]))
+        .map(|(&start, &end)| &text[start..end])
+        .filter(|message| !message.trim().is_empty())
+        .collect()
+}
+
+/// Takes the subject from the headers and the diff from the body. The diff
+/// runs from the first `diff --git` line to the `-- ` line that starts the
+/// signature, which would otherwise read as a removed line. A plain diff with
+/// no headers is taken whole.
+fn parse(message: &str) -> Patch {
+    let mut subject = String::new();
+    let mut diff = String::new();
+    let mut in_headers = true;
+    let mut in_subject = false;
+    let mut in_diff = false;
+
+    for line in message.lines() {
+        if line.starts_with("diff --git ") {
+            in_headers = false;
+            in_diff = true;
+        } else if in_diff && line == "-- " {
+            break;
+        } else if in_headers {
+            if let Some(value) = line.strip_prefix("Subject:") {
+                subject = value.trim().to_string();
+                in_subject = true;
+            } else if in_subject && line.starts_with([' ', '\t']) {
+                // Long subjects are folded onto continuation lines.
+                subject.push(' ');
+                subject.push_str(line.trim());
+            } else {
+                in_subject = false;
+            }
+            in_headers = !line.is_empty();
+        }
+
+        if in_diff {
+            diff.push_str(line);
+            diff.push('\n');
+        }
+    }
+    Patch { subject, diff }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIRST: &str = "\
+From 1111111111111111111111111111111111111111 Mon Sep 17 00:00:00 2001
+From: A U Thor <author@example.com>
+Subject: [PATCH 1/2] Extend f
+
+---
+ f | 1 +
+ 1 file changed, 1 insertion(+)
+
+diff --git a/f b/f
+index 7898192..422c2b7 100644
+--- a/f
++++ b/f
+@@ -1 +1,2 @@
+\x20a
++b
+--\x20
+2.39.5
+";
+
+    const SECOND: &str = "\
+From 2222222222222222222222222222222222222222 Mon Sep 17 00:00:00 2001
+From: A U Thor <author@example.com>
+Subject: [PATCH 2/2] Fold a long
+ subject
+
+diff --git a/g b/g
+new file mode 100644
+index 0000000..e69de29
+--\x20
+2.39.5
+";
+
+    #[test]
+    fn headers_and_signature_are_left_out() {
+        let patch = parse(FIRST);
+        assert_eq!(patch.subject, "[PATCH 1/2] Extend f");
+        assert!(patch.diff.starts_with("diff --git a/f b/f\n"));
+        assert!(patch.diff.ends_with("+b\n"));
+    }
+
+    #[test]
+    fn an_mbox_splits_into_its_messages() {
+        let mbox = format!("{}\n{}", FIRST, SECOND);
+        let messages = split_mbox(&mbox);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(
+            parse(messages[1]).subject,
+            "[PATCH 2/2] Fold a long subject"
+        );
+    }
+
+    #[test]
+    fn a_series_is_read_in_name_order() {
+        let dir = std::env::temp_dir().join(format!("giff-series-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("0002-Fold.patch"), SECOND).unwrap();
+        fs::write(dir.join("0001-Extend-f.patch"), FIRST).unwrap();
+        fs::write(dir.join("README"), "not a patch").unwrap();
+
+        let patches = read_dir(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        let subjects: Vec<_> = patches.unwrap().into_iter().map(|p| p.subject).collect();
+        assert_eq!(
+            subjects,
+            ["[PATCH 1/2] Extend f", "[PATCH 2/2] Fold a long subject"]
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use crate::error::GiffError;
+use crate::giff;
+use std::io::{self, BufRead, Write};
+
+const PICK_LIMIT: usize = 50;
+
+/// A commit offered by the picker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitEntry {
+    pub sha: String,
+    pub subject: String,
+}
+
+/// Parses `git log --oneline` output into entries, newest first.
+pub fn parse_oneline(log: &str) -> Vec<CommitEntry> {
+    log.lines()
+        .filter_map(|line| {
+            let (sha, subject) = line.split_once(' ').unwrap_or((line, ""));
+            (!sha.is_empty()).then(|| CommitEntry {
+                sha: sha.to_string(),
+                subject: subject.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Lists recent commits on stderr and asks for the two ends of the range on
+/// stdin. A single number diffs that commit against `HEAD`. The older of the
+/// two commits becomes the base whichever order they're given in.
+pub fn pick_range() -> Result<(String, String), GiffError> {
+    let git_args = ["log", "--oneline", "-n", &PICK_LIMIT.to_string()].map(String::from);
+    let mut command = giff::git();
+    let output = command
+        .args(&git_args)
+        .output()
+        .map_err(|err| giff::spawn_error(&command, err))?;
+    if !output.status.success() {
+        return Err(giff::command_error(&giff::command_line(&git_args), &output));
+    }
+    let commits = parse_oneline(&String::from_utf8_lossy(&output.stdout));
+    if commits.is_empty() {
+        return Err(GiffError::Invalid("No commits to pick from".to_string()));
+    }
+
+    let mut stderr = io::stderr();
+    for (idx, commit) in commits.iter().enumerate() {
+        writeln!(stderr, "{:>3}  {}  {}", idx + 1, commit.sha, commit.subject)?;
+    }
+    write!(stderr, "Base and head commit numbers (e.g. `5 1`): ")?;
+    stderr.flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+
+    let picks = answer
+        .split_whitespace()
+        .map(|word| match word.parse::<usize>() {
+            Ok(number) if (1..=commits.len()).contains(&number) => Ok(number - 1),
+            _ => Err(GiffError::Invalid(format!(
+                "'{}' is not a commit number between 1 and {}",
+                word,
+                commits.len()
+            ))),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match picks.as_slice() {
+        [single] => Ok((commits[*single].sha.clone(), "HEAD".to_string())),
+        [first, second] => {
+            let (older, newer) = if first > second {
+                (first, second)
+            } else {
+                (second, first)
+            };
+            Ok((commits[*older].sha.clone(), commits[*newer].sha.clone()))
+        }
+        _ => Err(GiffError::Invalid(
+            "Expected one or two commit numbers".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oneline_log_splits_into_sha_and_subject() {
+        let log = "3f1c2a9 Fix the parser\n9b0e7d4 Bump version\n\n1a2b3c4\n";
+        assert_eq!(
+            parse_oneline(log),
+            [
+                CommitEntry {
+                    sha: "3f1c2a9".to_string(),
+                    subject: "Fix the parser".to_string(),
+                },
+                CommitEntry {
+                    sha: "9b0e7d4".to_string(),
+                    subject: "Bump version".to_string(),
+                },
+                CommitEntry {
+                    sha: "1a2b3c4".to_string(),
+                    subject: String::new(),
+                },
+            ]
+        );
+        assert!(parse_oneline("").is_empty());
+    }
+}
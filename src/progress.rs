@@ -0,0 +1,70 @@
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const FRAME_INTERVAL: Duration = Duration::from_millis(80);
+
+/// A spinner drawn on stderr while the diff is acquired and parsed.
+///
+/// Does nothing when stderr isn't a terminal, so redirected or piped output
+/// never contains it.
+pub struct Spinner {
+    message: Arc<Mutex<String>>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Spinner {
+    pub fn start(message: &str) -> Spinner {
+        let message = Arc::new(Mutex::new(message.to_string()));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let handle = io::stderr().is_terminal().then(|| {
+            let message = Arc::clone(&message);
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                let mut stderr = io::stderr();
+                for frame in FRAMES.iter().cycle() {
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let _ = write!(stderr, "\r\x1b[2K{} {}", frame, message.lock().unwrap());
+                    let _ = stderr.flush();
+                    thread::sleep(FRAME_INTERVAL);
+                }
+                let _ = write!(stderr, "\r\x1b[2K");
+                let _ = stderr.flush();
+            })
+        });
+
+        Spinner {
+            message,
+            stop,
+            handle,
+        }
+    }
+
+    /// A spinner that never draws anything.
+    pub fn disabled() -> Spinner {
+        Spinner {
+            message: Arc::new(Mutex::new(String::new())),
+            stop: Arc::new(AtomicBool::new(true)),
+            handle: None,
+        }
+    }
+
+    pub fn set_message(&self, message: String) {
+        *self.message.lock().unwrap() = message;
+    }
+
+    /// Stops the spinner and clears its line.
+    pub fn finish(mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use crate::parser::{FileChanges, Origin, Side};
+use crate::table;
+use std::collections::HashSet;
+
+/// Lists where each hunk's changes start as `file:line: message`, which
+/// editors' default error formats read (e.g. Vim's `:cexpr`).
+///
+/// A hunk gets one `added:` entry at its first added line, in the new file,
+/// and one `removed:` entry at its first removed line, in the old file.
+pub fn entries(file_changes: &FileChanges) -> String {
+    let mut entries = String::new();
+    for (file, change) in file_changes {
+        let old_file = change.old_path.as_deref().unwrap_or(file);
+        let mut added = HashSet::new();
+        let mut removed = HashSet::new();
+
+        for (base_num, head_num, origin, content) in
+            table::unified_lines(&change.base_lines, &change.head_lines)
+        {
+            let entry = match (origin, base_num, head_num) {
+                (Origin::Added, _, Some(num)) => added
+                    .insert(change.hunk_of(num, Side::Head))
+                    .then_some((file.as_str(), num, "added", content)),
+                (Origin::Removed, Some(num), _) => removed
+                    .insert(change.hunk_of(num, Side::Base))
+                    .then_some((old_file, num, "removed", content)),
+                _ => None,
+            };
+            if let Some((path, num, kind, content)) = entry {
+                let entry = format!("{}:{}: {}: {}", path, num, kind, content.trim());
+                entries.push_str(entry.trim_end());
+                entries.push('\n');
+            }
+        }
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_diff_output;
+
+    #[test]
+    fn each_hunk_gets_its_first_added_and_removed_line() {
+        let diff = "\
+diff --git a/old.rs b/src/lib.rs
+similarity index 80%
+rename from old.rs
+rename to src/lib.rs
+--- a/old.rs
++++ b/src/lib.rs
+@@ -1,4 +1,4 @@
+\x20fn a() {}
+-fn b() {}
+-fn c() {}
++fn b(x: u8) {}
++fn c(x: u8) {}
+\x20fn d() {}
+@@ -20,3 +20,4 @@
+\x20fn t() {}
++fn u() {}
++fn v() {}
+\x20fn w() {}
+diff --git a/z.txt b/z.txt
+--- a/z.txt
++++ b/z.txt
+@@ -5,2 +5,1 @@
+-gone
+\x20kept
+";
+        assert_eq!(
+            entries(&parse_diff_output(diff)),
+            "\
+old.rs:2: removed: fn b() {}
+src/lib.rs:2: added: fn b(x: u8) {}
+src/lib.rs:21: added: fn u() {}
+z.txt:5: removed: gone
+"
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use crate::color;
+use crate::error::GiffError;
+use crate::giff;
+use comfy_table::{Cell, Color, Table};
+use regex::Regex;
+
+/// How a commit in the old version of a branch relates to one in the new.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    /// `=`: the same change
+    Unchanged,
+    /// `!`: the change was modified
+    Modified,
+    /// `<`: only in the old version
+    Removed,
+    /// `>`: only in the new version
+    Added,
+}
+
+/// One line of `git range-diff`'s commit correspondence, with the
+/// diff-of-diffs git prints below modified pairs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// Position and short SHA in the old version, if the commit is there.
+    pub old: Option<(usize, String)>,
+    pub new: Option<(usize, String)>,
+    pub relation: Relation,
+    pub subject: String,
+    pub details: Vec<String>,
+}
+
+/// Runs `git range-diff` between two versions of a branch built on `base`.
+pub fn run(base: &str, old_tip: &str, new_tip: &str) -> Result<String, GiffError> {
+    if let Some(rev) = [base, old_tip, new_tip]
+        .into_iter()
+        .find(|rev| !giff::rev_exists(rev))
+    {
+        return Err(GiffError::BadRevision(rev.to_string()));
+    }
+
+    let git_args = ["range-diff", "--no-color", base, old_tip, new_tip].map(String::from);
+    let mut command = giff::git();
+    let output = command
+        .args(&git_args)
+        .output()
+        .map_err(|err| giff::spawn_error(&command, err))?;
+    if !output.status.success() {
+        return Err(giff::command_error(&giff::command_line(&git_args), &output));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+pub fn parse(output: &str) -> Vec<Entry> {
+    let pair_regex =
+        Regex::new(r"^\s*(\d+|-):\s+([0-9a-f]+|-+) ([=!<>]) \s*(\d+|-):\s+([0-9a-f]+|-+) (.*)$")
+            .unwrap();
+    let side = |num: &str, sha: &str| num.parse().ok().map(|num| (num, sha.to_string()));
+
+    let mut entries: Vec<Entry> = Vec::new();
+    for line in output.lines() {
+        if let Some(caps) = pair_regex.captures(line) {
+            entries.push(Entry {
+                old: side(&caps[1], &caps[2]),
+                new: side(&caps[4], &caps[5]),
+                relation: match &caps[3] {
+                    "=" => Relation::Unchanged,
+                    "!" => Relation::Modified,
+                    "<" => Relation::Removed,
+                    _ => Relation::Added,
+                },
+                subject: caps[6].to_string(),
+                details: Vec::new(),
+            });
+        } else if let Some(entry) = entries.last_mut() {
+            entry
+                .details
+                .push(line.strip_prefix("    ").unwrap_or(line).to_string());
+        }
+    }
+    entries
+}
+
+/// Re-abbreviates the SHAs `git range-diff` printed, which ignores
+/// `--abbrev`, to `len` hex digits.
+pub fn abbreviate(entries: &mut [Entry], len: usize) {
+    for entry in entries {
+        for (_, sha) in entry.old.iter_mut().chain(entry.new.iter_mut()) {
+            if let Some(short) = giff::short_sha(sha, Some(len)) {
+                *sha = short;
+            }
+        }
+    }
+}
+
+/// One row per commit pair, followed by the diff-of-diffs for modified ones.
+pub fn populate_table(table: &mut Table, entries: &[Entry]) {
+    let side = |side: &Option<(usize, String)>| match side {
+        Some((num, sha)) => format!("{}: {}", num, sha),
+        None => "-".to_string(),
+    };
+
+    for entry in entries {
+        let (marker, color) = match entry.relation {
+            Relation::Unchanged => ("=", Color::DarkGrey),
+            Relation::Modified => ("!", Color::Yellow),
+            Relation::Removed => ("<", Color::Red),
+            Relation::Added => (">", Color::Green),
+        };
+        table.add_row(vec![
+            Cell::new(side(&entry.old)).fg(color::adapt(color)),
+            Cell::new(marker).fg(color::adapt(color)),
+            Cell::new(side(&entry.new)).fg(color::adapt(color)),
+            Cell::new(&entry.subject).fg(color::adapt(color)),
+        ]);
+
+        // The first column of a detail line says whether it is only in the old
+        // version of the patch or only in the new one.
+        for line in &entry.details {
+            let cell = Cell::new(line);
+            let cell = match line.chars().next() {
+                Some('-') => cell.fg(color::adapt(Color::Red)),
+                Some('+') => cell.fg(color::adapt(Color::Green)),
+                _ => cell,
+            };
+            table.add_row(vec![Cell::new(""), Cell::new(""), Cell::new(""), cell]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &str = "\
+1:  cee1a23 = 1:  cee1a23 Add a
+2:  561f34d ! 2:  c416d0e Add b
+    @@ b (new)
+    \x20+20
+    ++21
+3:  072bb39 < -:  ------- Add c
+-:  ------- > 3:  fcdcf84 Add d
+";
+
+    #[test]
+    fn pairs_are_parsed_with_their_relation() {
+        let entries = parse(OUTPUT);
+        let relations: Vec<_> = entries.iter().map(|entry| entry.relation).collect();
+        assert_eq!(
+            relations,
+            [
+                Relation::Unchanged,
+                Relation::Modified,
+                Relation::Removed,
+                Relation::Added
+            ]
+        );
+        assert_eq!(entries[0].old, Some((1, "cee1a23".to_string())));
+        assert_eq!(entries[1].new, Some((2, "c416d0e".to_string())));
+        assert_eq!(entries[1].subject, "Add b");
+    }
+
+    #[test]
+    fn missing_sides_are_none() {
+        let entries = parse(OUTPUT);
+        assert_eq!(entries[2].new, None);
+        assert_eq!(entries[3].old, None);
+        assert_eq!(entries[3].new, Some((3, "fcdcf84".to_string())));
+    }
+
+    #[test]
+    fn diff_of_diffs_goes_with_the_modified_pair() {
+        let entries = parse(OUTPUT);
+        assert_eq!(entries[1].details, ["@@ b (new)", " +20", "++21"]);
+        assert!(entries[0].details.is_empty());
+    }
+}
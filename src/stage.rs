@@ -0,0 +1,305 @@
+use crate::diff_options::DiffOptions;
+use crate::error::GiffError;
+use crate::giff;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, Write};
+use std::process::Stdio;
+
+/// What to do with the hunk on screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Answer {
+    Stage,
+    Skip,
+    /// Ask about each group of changes in the hunk separately.
+    Split,
+    Quit,
+}
+
+/// One hunk of a file's patch, which can be staged whole or one group of
+/// changes at a time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    /// Where the hunk starts in the index's version of the file.
+    old_start: usize,
+    /// Where it starts in the working tree's, with every hunk before it.
+    new_start: usize,
+    /// The lines after the `@@` header, each with its marker.
+    lines: Vec<String>,
+}
+
+impl Hunk {
+    /// Reads a hunk from its `@@` header on.
+    pub fn parse(text: &str) -> Option<Hunk> {
+        let mut lines = text.lines();
+        let (old, new) = lines.next()?.strip_prefix("@@ -")?.split_once(" +")?;
+        let start = |range: &str| range.split([',', ' ']).next()?.parse().ok();
+        Some(Hunk {
+            old_start: start(old)?,
+            new_start: start(new)?,
+            lines: lines.map(String::from).collect(),
+        })
+    }
+
+    /// Which group of changes each line belongs to: a group is a run of `-`
+    /// and `+` lines, with any `\ No newline` marker after one of them.
+    /// Context lines belong to none.
+    fn line_groups(&self) -> Vec<Option<usize>> {
+        let mut groups = Vec::new();
+        let mut count = 0;
+        let mut in_group = false;
+        for line in &self.lines {
+            let changed = line.starts_with(['-', '+']) || (in_group && line.starts_with('\\'));
+            if changed && !in_group {
+                count += 1;
+            }
+            in_group = changed;
+            groups.push(changed.then(|| count - 1));
+        }
+        groups
+    }
+
+    /// How many groups of changes the hunk has. One with more than one can be
+    /// split.
+    pub fn groups(&self) -> usize {
+        self.line_groups()
+            .into_iter()
+            .flatten()
+            .max()
+            .map_or(0, |last| last + 1)
+    }
+
+    /// The hunk with only the groups of changes `keep` is true for. The rest
+    /// are undone, as if never made: their removed lines stay as context and
+    /// their added lines are dropped.
+    pub fn select(&self, keep: &[bool]) -> Hunk {
+        let mut lines = Vec::new();
+        let mut dropped_previous = false;
+        for (line, group) in self.lines.iter().zip(self.line_groups()) {
+            let kept = group.is_none_or(|group| keep.get(group).copied().unwrap_or(false));
+            if kept {
+                lines.push(line.clone());
+                dropped_previous = false;
+            } else if let Some(content) = line.strip_prefix('-') {
+                lines.push(format!(" {}", content));
+                dropped_previous = false;
+            } else if line.starts_with('+') {
+                dropped_previous = true;
+            } else if !dropped_previous {
+                // A `\ No newline` marker, kept only with its line.
+                lines.push(line.clone());
+            }
+        }
+        Hunk {
+            old_start: self.old_start,
+            new_start: self.new_start,
+            lines,
+        }
+    }
+
+    /// Whether the hunk still adds or removes anything.
+    pub fn has_changes(&self) -> bool {
+        self.lines.iter().any(|line| line.starts_with(['-', '+']))
+    }
+
+    /// The hunk as a patch after `header`, numbered as in the full diff.
+    pub fn display(&self, header: &str) -> String {
+        let mut patch = header.to_string();
+        self.write(&mut patch, self.new_start);
+        patch
+    }
+
+    fn write(&self, patch: &mut String, new_start: usize) {
+        let _ = writeln!(
+            patch,
+            "@@ -{},{} +{},{} @@",
+            self.old_start,
+            self.count([' ', '-']),
+            new_start,
+            self.count([' ', '+'])
+        );
+        for line in &self.lines {
+            patch.push_str(line);
+            patch.push('\n');
+        }
+    }
+
+    fn count(&self, markers: [char; 2]) -> usize {
+        self.lines
+            .iter()
+            .filter(|line| line.starts_with(markers))
+            .count()
+    }
+}
+
+/// The unstaged changes in the working tree, as `git diff` prints them.
+pub fn worktree_diff(paths: &[String]) -> Result<String, GiffError> {
+    let git_args = DiffOptions::unstaged().paths(paths).to_args();
+    let mut command = giff::git();
+    let output = command
+        .args(&git_args)
+        .output()
+        .map_err(|err| giff::spawn_error(&command, err))?;
+    if !output.status.success() {
+        return Err(giff::command_error(&giff::command_line(&git_args), &output));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Splits a file's patch into its header (everything up to the first `@@`
+/// line) and its hunks.
+pub fn split_hunks(patch: &str) -> (String, Vec<Hunk>) {
+    let mut header = String::new();
+    let mut hunks: Vec<String> = Vec::new();
+    for line in patch.split_inclusive('\n') {
+        if line.starts_with("@@ ") {
+            hunks.push(String::new());
+        }
+        match hunks.last_mut() {
+            Some(hunk) => hunk.push_str(line),
+            None => header.push_str(line),
+        }
+    }
+    let hunks = hunks.iter().filter_map(|hunk| Hunk::parse(hunk)).collect();
+    (header, hunks)
+}
+
+/// A patch of `header` followed by `hunks`, with fresh `@@` headers. Each
+/// hunk's position in the new file is worked out from the lines the hunks
+/// before it add and remove, so any subset of a file's hunks applies.
+pub fn patch(header: &str, hunks: &[Hunk]) -> String {
+    let mut patch = header.to_string();
+    let mut offset: isize = 0;
+    for hunk in hunks {
+        let old_len = hunk.count([' ', '-']);
+        let new_len = hunk.count([' ', '+']);
+        // An empty side is numbered from the line before it, e.g. `-0,0` for
+        // a new file.
+        let new_start = hunk.old_start as isize + offset + isize::from(old_len == 0)
+            - isize::from(new_len == 0);
+        hunk.write(&mut patch, new_start.max(0) as usize);
+        offset += new_len as isize - old_len as isize;
+    }
+    patch
+}
+
+/// Asks on stderr whether to stage a hunk, reading the answer from stdin
+/// until it's one of `y`, `n`, `q` or, when `can_split`, `s`. The end of input
+/// quits.
+pub fn ask(can_split: bool) -> io::Result<Answer> {
+    let mut stderr = io::stderr();
+    let choices = if can_split { "y,n,s,q" } else { "y,n,q" };
+    loop {
+        write!(stderr, "Stage this hunk [{}]? ", choices)?;
+        stderr.flush()?;
+
+        let mut answer = String::new();
+        if io::stdin().lock().read_line(&mut answer)? == 0 {
+            writeln!(stderr)?;
+            return Ok(Answer::Quit);
+        }
+        match answer.trim() {
+            "y" => return Ok(Answer::Stage),
+            "n" => return Ok(Answer::Skip),
+            "s" if can_split => return Ok(Answer::Split),
+            "q" => return Ok(Answer::Quit),
+            _ if can_split => writeln!(
+                stderr,
+                "y - stage this hunk, n - skip it, s - split it into smaller hunks, q - stop"
+            )?,
+            _ => writeln!(stderr, "y - stage this hunk, n - skip it, q - stop")?,
+        }
+    }
+}
+
+/// Adds `patch` to the index with `git apply --cached`, leaving the working
+/// tree alone.
+pub fn apply_cached(patch: &str) -> Result<(), GiffError> {
+    let mut command = giff::git();
+    command
+        .args(["apply", "--cached", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut child = command
+        .spawn()
+        .map_err(|err| giff::spawn_error(&command, err))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(patch.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(giff::command_error("git apply --cached", &output));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATCH: &str = "\
+diff --git a/nums b/nums
+--- a/nums
++++ b/nums
+@@ -1,6 +1,7 @@
+\x201
+-2
++two
++2.5
+\x203
+\x204
+-5
++five
+\x206
+@@ -20,3 +21,4 @@
+\x2020
++20.5
+\x2021
+\x2022
+";
+
+    #[test]
+    fn hunks_split_into_groups_of_changes() {
+        let (header, hunks) = split_hunks(PATCH);
+        assert_eq!(header, "diff --git a/nums b/nums\n--- a/nums\n+++ b/nums\n");
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(hunks[0].groups(), 2);
+        assert_eq!(hunks[1].groups(), 1);
+    }
+
+    #[test]
+    fn unselected_groups_are_undone() {
+        let (_, hunks) = split_hunks(PATCH);
+        let second = hunks[0].select(&[false, true]);
+        assert_eq!(
+            second.lines,
+            [" 1", " 2", " 3", " 4", "-5", "+five", " 6"].map(String::from)
+        );
+        assert!(second.has_changes());
+        assert!(!hunks[0].select(&[false, false]).has_changes());
+    }
+
+    #[test]
+    fn later_hunks_are_renumbered_for_the_ones_staged() {
+        let (header, hunks) = split_hunks(PATCH);
+        let last = patch(&header, &[hunks[1].clone()]);
+        assert!(last.ends_with("@@ -20,3 +20,4 @@\n 20\n+20.5\n 21\n 22\n"));
+
+        let first = hunks[0].select(&[true, false]);
+        let both = patch(&header, &[first, hunks[1].clone()]);
+        assert!(both.contains("@@ -1,6 +1,7 @@\n 1\n-2\n+two\n+2.5\n 3\n 4\n 5\n 6\n"));
+        assert!(both.ends_with("@@ -20,3 +21,4 @@\n 20\n+20.5\n 21\n 22\n"));
+    }
+
+    #[test]
+    fn no_newline_marker_goes_with_its_line() {
+        let hunk =
+            Hunk::parse("@@ -1,2 +1,2 @@\n-a\n+b\n x\n-y\n+z\n\\ No newline at end of file\n")
+                .unwrap();
+        assert_eq!(hunk.groups(), 2);
+        assert_eq!(
+            hunk.select(&[true, false]).lines,
+            ["-a", "+b", " x", " y"].map(String::from)
+        );
+    }
+}
@@ -0,0 +1,265 @@
+use crate::binary;
+use crate::color;
+use crate::parser::{FileChange, FileChanges, FileStatus};
+use crossterm::style::Stylize;
+
+/// The widest a `+`/`-` bar gets, as with git's default `--stat-graph-width`.
+pub const BAR_WIDTH: usize = 40;
+
+/// How many of `width` bar characters go to additions and to deletions.
+///
+/// Counts are scaled down, like `git diff --stat`, only when the largest
+/// change (`max_changes`) wouldn't fit; any non-zero count keeps at least one
+/// character.
+pub fn bar_lengths(
+    additions: usize,
+    deletions: usize,
+    max_changes: usize,
+    width: usize,
+) -> (usize, usize) {
+    if max_changes <= width {
+        return (additions, deletions);
+    }
+    let scale = |count: usize| {
+        if count == 0 {
+            0
+        } else {
+            1 + count * (width - 1) / max_changes
+        }
+    };
+    let total = scale(additions + deletions);
+    let deletions = scale(deletions).min(total);
+    (total - deletions, deletions)
+}
+
+/// Formats the changes like `git diff --compact-summary`: one line per file
+/// with its change count and bar, followed by the totals.
+pub fn compact_summary(file_changes: &FileChanges, color: bool) -> String {
+    summary_with(file_changes, color, binary::blob_size)
+}
+
+/// [`compact_summary`], reading binary files' sizes with `blob_size`.
+fn summary_with(
+    file_changes: &FileChanges,
+    color: bool,
+    blob_size: impl Fn(&str) -> Option<usize>,
+) -> String {
+    let names: Vec<String> = file_changes
+        .iter()
+        .map(|(file, change)| {
+            let name = match &change.old_path {
+                Some(old_path) => format!("{} => {}", old_path, file),
+                None => file.clone(),
+            };
+            match annotation(change) {
+                Some(note) => format!("{} ({})", name, note),
+                None => name,
+            }
+        })
+        .collect();
+    let counts: Vec<(usize, usize)> = file_changes
+        .values()
+        .map(|change| (change.additions(), change.deletions()))
+        .collect();
+    let binaries: Vec<Option<String>> = file_changes
+        .values()
+        .map(|change| change.binary.then(|| binary_sizes(change, &blob_size)))
+        .collect();
+
+    let name_width = names.iter().map(|name| name.chars().count()).max();
+    let name_width = name_width.unwrap_or(0);
+    let max_changes = counts.iter().map(|(a, d)| a + d).max().unwrap_or(0);
+    let mut count_width = max_changes.to_string().len();
+    if binaries.iter().any(Option::is_some) {
+        // Wide enough for `Bin`.
+        count_width = count_width.max(3);
+    }
+
+    let mut summary = String::new();
+    for ((name, (additions, deletions)), binary) in names.iter().zip(&counts).zip(&binaries) {
+        if let Some(sizes) = binary {
+            summary.push_str(&format!(" {:<name_width$} | {}\n", name, sizes));
+            continue;
+        }
+        let (plus, minus) = bar_lengths(*additions, *deletions, max_changes, BAR_WIDTH);
+        let (plus, minus) = ("+".repeat(plus), "-".repeat(minus));
+        let (plus, minus) = if color && color::is_light_background() {
+            (plus.dark_green().to_string(), minus.dark_red().to_string())
+        } else if color {
+            (plus.green().to_string(), minus.red().to_string())
+        } else {
+            (plus, minus)
+        };
+        let line = format!(
+            " {:<name_width$} | {:>count_width$} {}{}",
+            name,
+            additions + deletions,
+            plus,
+            minus
+        );
+        summary.push_str(line.trim_end());
+        summary.push('\n');
+    }
+
+    let additions: usize = counts.iter().map(|(a, _)| a).sum();
+    let deletions: usize = counts.iter().map(|(_, d)| d).sum();
+    let mut totals = vec![format!(
+        "{} {} changed",
+        names.len(),
+        if names.len() == 1 { "file" } else { "files" }
+    )];
+    // Like git, a count is left out only when the other one isn't zero.
+    if additions > 0 || deletions == 0 {
+        totals.push(format!(
+            "{} {}(+)",
+            additions,
+            if additions == 1 {
+                "insertion"
+            } else {
+                "insertions"
+            }
+        ));
+    }
+    if deletions > 0 || additions == 0 {
+        totals.push(format!(
+            "{} {}(-)",
+            deletions,
+            if deletions == 1 {
+                "deletion"
+            } else {
+                "deletions"
+            }
+        ));
+    }
+    summary.push_str(&format!(" {}\n", totals.join(", ")));
+    summary
+}
+
+/// A binary file's sizes in place of its count, as `Bin 24 -> 26 bytes`, or
+/// just `Bin` when a blob can't be read.
+fn binary_sizes(change: &FileChange, blob_size: impl Fn(&str) -> Option<usize>) -> String {
+    let sizes = change
+        .blobs
+        .as_ref()
+        .and_then(|(old, new)| Some((blob_size(old)?, blob_size(new)?)));
+    match sizes {
+        Some((old, new)) => format!("Bin {} -> {} bytes", old, new),
+        None => "Bin".to_string(),
+    }
+}
+
+/// The note git's compact summary puts after a path: `new`, `gone`, or a
+/// change in the executable bit or to a symlink.
+fn annotation(change: &FileChange) -> Option<String> {
+    let mode_suffix = |mode: Option<&str>| match mode {
+        Some("100755") => " +x",
+        Some("120000") => " +l",
+        _ => "",
+    };
+    match change.status {
+        FileStatus::Added => Some(format!("new{}", mode_suffix(change.new_mode.as_deref()))),
+        FileStatus::Deleted => Some("gone".to_string()),
+        _ => match (change.old_mode.as_deref(), change.new_mode.as_deref()) {
+            (Some(old), Some(new)) if old != new => {
+                let new_suffix = mode_suffix(Some(new));
+                if new_suffix.is_empty() {
+                    Some(format!("mode{}", mode_suffix(Some(old)).replace('+', "-")))
+                } else {
+                    Some(format!("mode{}", new_suffix))
+                }
+            }
+            _ => None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_diff_output;
+
+    #[test]
+    fn small_counts_are_not_scaled() {
+        assert_eq!(bar_lengths(3, 2, 5, BAR_WIDTH), (3, 2));
+        assert_eq!(bar_lengths(25, 15, 40, BAR_WIDTH), (25, 15));
+    }
+
+    #[test]
+    fn large_counts_are_scaled_to_the_width() {
+        assert_eq!(bar_lengths(100, 0, 100, BAR_WIDTH), (40, 0));
+        assert_eq!(bar_lengths(50, 50, 100, BAR_WIDTH), (20, 20));
+        assert_eq!(bar_lengths(0, 1000, 1000, BAR_WIDTH), (0, 40));
+    }
+
+    #[test]
+    fn scaled_counts_keep_at_least_one_character() {
+        assert_eq!(bar_lengths(1, 999, 1000, BAR_WIDTH), (1, 39));
+        assert_eq!(bar_lengths(1, 0, 1000, BAR_WIDTH), (1, 0));
+    }
+
+    #[test]
+    fn compact_summary_matches_git_on_a_mixed_diff() {
+        let diff = "\
+diff --git a/f b/f
+old mode 100644
+new mode 100755
+index 01e79c3..dd29ffc
+--- a/f
++++ b/f
+@@ -1,3 +1,4 @@
+\x201
+-2
++TWO
+\x203
++4
+diff --git a/gone b/gone
+deleted file mode 100644
+index 01058d8..0000000
+--- a/gone
++++ /dev/null
+@@ -1 +0,0 @@
+-g
+diff --git a/img.png b/img.png
+new file mode 100644
+index 0000000..2e3d1da
+Binary files /dev/null and b/img.png differ
+diff --git a/run b/run
+new file mode 100755
+index 0000000..4286f42
+--- /dev/null
++++ b/run
+@@ -0,0 +1 @@
++r
+";
+        let sizes = |blob: &str| match blob {
+            "0000000" => Some(0),
+            "2e3d1da" => Some(26),
+            _ => None,
+        };
+        // `git diff --compact-summary` on the same commits.
+        let expected = "\
+\x20f (mode +x)   |   3 ++-
+\x20gone (gone)   |   1 -
+\x20img.png (new) | Bin 0 -> 26 bytes
+\x20run (new +x)  |   1 +
+\x204 files changed, 3 insertions(+), 2 deletions(-)
+";
+        assert_eq!(
+            summary_with(&parse_diff_output(diff), false, sizes),
+            expected
+        );
+    }
+
+    #[test]
+    fn unreadable_binaries_and_no_line_changes() {
+        let diff = "\
+diff --git a/img.png b/img.png
+index 1111111..2222222 100644
+Binary files a/img.png and b/img.png differ
+";
+        assert_eq!(
+            summary_with(&parse_diff_output(diff), false, |_| None),
+            " img.png | Bin\n 1 file changed, 0 insertions(+), 0 deletions(-)\n"
+        );
+    }
+}
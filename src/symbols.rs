@@ -0,0 +1,101 @@
+use crate::parser::{FileChanges, Origin};
+use crate::table;
+use regex::Regex;
+use std::path::Path;
+
+/// Patterns for lines that start a definition, by file extension. They're
+/// rough, matching how such headers usually begin rather than parsing.
+const PATTERNS: [(&[&str], &str); 5] = [
+    (
+        &["rs"],
+        r"^\s*(pub(\([^)]*\))?\s+)?((async|const|unsafe|extern\s+\S+)\s+)*((fn|struct|enum|union|trait|impl|mod)\b|macro_rules!)",
+    ),
+    (&["py"], r"^\s*(async\s+)?(def|class)\s"),
+    (&["go"], r"^(func|type)\s"),
+    (&["rb"], r"^\s*(def|class|module)\s"),
+    (
+        &["js", "jsx", "mjs", "cjs", "ts", "tsx"],
+        r"^\s*(export\s+)?(default\s+)?(async\s+)?(function\b|class\s|interface\s|(const|let)\s+\w+\s*=\s*(async\s*)?(\(|function\b))",
+    ),
+];
+
+/// Lists the changed lines that look like function, class or similar
+/// headers as `file:line: added|removed: header`, for jumping to the
+/// definitions a diff touches from an editor's quickfix list. Files in
+/// languages without a pattern are left out.
+pub fn entries(file_changes: &FileChanges) -> String {
+    let patterns: Vec<(&[&str], Regex)> = PATTERNS
+        .iter()
+        .map(|(extensions, pattern)| (*extensions, Regex::new(pattern).unwrap()))
+        .collect();
+
+    let mut entries = String::new();
+    for (file, change) in file_changes {
+        let extension = Path::new(file).extension().and_then(|ext| ext.to_str());
+        let Some((_, pattern)) = patterns
+            .iter()
+            .find(|(extensions, _)| extension.is_some_and(|ext| extensions.contains(&ext)))
+        else {
+            continue;
+        };
+        let old_file = change.old_path.as_deref().unwrap_or(file);
+
+        for (base_num, head_num, origin, content) in
+            table::unified_lines(&change.base_lines, &change.head_lines)
+        {
+            let entry = match (origin, base_num, head_num) {
+                (Origin::Added, _, Some(num)) => Some((file.as_str(), num, "added")),
+                (Origin::Removed, Some(num), _) => Some((old_file, num, "removed")),
+                _ => None,
+            };
+            if let Some((path, num, kind)) = entry.filter(|_| pattern.is_match(content)) {
+                let entry = format!("{}:{}: {}: {}", path, num, kind, content.trim());
+                entries.push_str(&entry);
+                entries.push('\n');
+            }
+        }
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_diff_output;
+
+    #[test]
+    fn rust_headers_are_listed() {
+        let diff = "\
+diff --git a/src/lib.rs b/src/lib.rs
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,5 +1,9 @@
+-fn old_name() {}
++pub(crate) async fn new_name() {}
+\x20
++pub struct Config {
++    pub fn_count: usize,
++}
+\x20impl Display for Thing {
+-    let x = 1;
++    let y = 2;
+\x20}
++macro_rules! shout {}
+diff --git a/README.md b/README.md
+--- a/README.md
++++ b/README.md
+@@ -1 +1 @@
+-fn not_code() {}
++fn still_not_code() {}
+";
+        assert_eq!(
+            entries(&parse_diff_output(diff)),
+            "\
+src/lib.rs:1: removed: fn old_name() {}
+src/lib.rs:1: added: pub(crate) async fn new_name() {}
+src/lib.rs:3: added: pub struct Config {
+src/lib.rs:9: added: macro_rules! shout {}
+"
+        );
+    }
+}
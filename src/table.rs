@@ -1,57 +1,836 @@
-use comfy_table::{Cell, Color, Table};
-use std::collections::HashMap;
+use crate::args::{Args, LineFilter, View};
+use crate::binary;
+use crate::color;
+use crate::giff;
+use crate::moved::{self, MovedLines};
+use crate::parser::{
+    conflict_marker, ConflictMarker, FileChange, FileChanges, FileStatus, LineChange, Origin, Side,
+};
+use crate::stat;
+use comfy_table::{Attribute, Cell, CellAlignment, Color, Table};
+use std::collections::HashSet;
 
-pub fn populate_table(
-    table: &mut Table,
-    file_changes: HashMap<String, (Vec<(usize, String)>, Vec<(usize, String)>)>,
-) {
-    for (file, (base_lines, head_lines)) in file_changes {
-        let max_lines = base_lines.len().max(head_lines.len());
+/// Lines of context kept around each change with `--only-changes`.
+const ONLY_CHANGES_CONTEXT: usize = 1;
 
+/// Lines longer than this many characters (e.g. minified files) are cut short.
+const LONG_LINE_CHARS: usize = 10_000;
+
+/// How much of an over-long line is still shown.
+const LONG_LINE_PREVIEW: usize = 200;
+
+/// The widest a `--stat-bars` bar gets, narrower than git's so the summary
+/// table still fits beside long paths.
+const SUMMARY_BAR_WIDTH: usize = 20;
+
+/// Lines that were moved rather than changed, as with `git diff --color-moved`.
+const MOVED_COLOR: Color = Color::Rgb {
+    r: 95,
+    g: 135,
+    b: 215,
+};
+
+pub fn populate_table(table: &mut Table, file_changes: FileChanges, args: &Args) {
+    for (file, change) in file_changes {
         // Add the file name row
-        table.add_row(vec![file.clone(), "".to_string(), "".to_string()]);
+        let mut name = Cell::new(change.display_path(&file));
+        if args.view == View::Inline {
+            // Without a file column, set file names apart from their lines.
+            name = name.add_attribute(Attribute::Bold);
+        }
+        if change.old_path.is_some() {
+            name = name.fg(color::adapt(Color::Yellow));
+        }
+        table.add_row(note_row(name, args.view, true));
+
+        if let Some(submodule) = &change.submodule {
+            let len = giff::abbrev_len(args);
+            let summary = format!(
+                "submodule {}: {} → {}",
+                file,
+                short_commit(submodule.old_commit.as_deref(), len),
+                short_commit(submodule.new_commit.as_deref(), len)
+            );
+            table.add_row(note_row(
+                Cell::new(summary).fg(color::adapt(Color::Cyan)),
+                args.view,
+                false,
+            ));
+        }
+
+        if change.binary {
+            let summary = binary::summary(&file, &change)
+                .map(|summary| format!("binary: {}", summary))
+                .unwrap_or_else(|| "binary file changed".to_string());
+            table.add_row(note_row(
+                Cell::new(summary).fg(color::adapt(Color::Cyan)),
+                args.view,
+                false,
+            ));
+        }
+
+        if let Some((old, new)) = change.symlink_targets() {
+            let summary = format!(
+                "symlink: {} → {}",
+                old.unwrap_or("(none)"),
+                new.unwrap_or("(none)")
+            );
+            table.add_row(note_row(
+                Cell::new(summary).fg(color::adapt(Color::Cyan)),
+                args.view,
+                false,
+            ));
+            continue;
+        }
+
+        let no_content = change.base_lines.is_empty() && change.head_lines.is_empty();
+        if no_content && !change.binary && change.submodule.is_none() {
+            table.add_row(note_row(
+                Cell::new(no_content_note(&change)).fg(color::adapt(Color::DarkGrey)),
+                args.view,
+                false,
+            ));
+            continue;
+        }
+
+        if let Some((from, to)) = change.line_endings.filter(|_| !args.raw_line_endings) {
+            let note = format!("(line endings changed: {} → {})", from, to);
+            table.add_row(note_row(
+                Cell::new(note).fg(color::adapt(Color::DarkGrey)),
+                args.view,
+                false,
+            ));
+            continue;
+        }
+
+        let change = match args.only {
+            // Dropping the other side's changes first leaves only context
+            // around the kept ones.
+            Some(filter) => only_changes(&keep_changes(&change, filter), ONLY_CHANGES_CONTEXT),
+            None if args.only_changes => only_changes(&change, ONLY_CHANGES_CONTEXT),
+            None => change,
+        };
+        let moved = moved_lines(&change, args);
+        let view_rows = |change: &FileChange| match args.view {
+            View::SideBySide => side_by_side_rows(change, &moved, args),
+            View::Unified => unified_rows(change, &moved, args),
+            View::Inline => inline_rows(change, &moved, args),
+        };
+        let rows = if args.decorations {
+            decorated_rows(&file, &change, args, view_rows)
+        } else {
+            view_rows(&change)
+        };
+        let max_lines = rows.len();
+        let max_len = match args.max_lines {
+            Some(limit) => max_lines.min(limit),
+            None => max_lines,
+        };
+
+        // Add rows to the table
+        for row in rows.into_iter().take(max_len) {
+            table.add_row(row);
+        }
+
+        if max_len < max_lines {
+            let marker = format!("… {} more lines (truncated) …", max_lines - max_len);
+            table.add_row(note_row(
+                Cell::new(marker).fg(color::adapt(Color::DarkGrey)),
+                args.view,
+                false,
+            ));
+        } else if let Some(note) = no_newline_note(change.no_newline) {
+            table.add_row(note_row(
+                Cell::new(note).fg(color::adapt(Color::DarkGrey)),
+                args.view,
+                false,
+            ));
+        }
+    }
+}
+
+/// Git's marker for a file whose last line has no final newline, naming the
+/// side when only one is affected.
+fn no_newline_note(no_newline: (bool, bool)) -> Option<&'static str> {
+    match no_newline {
+        (true, true) => Some("\\ No newline at end of file"),
+        (true, false) => Some("\\ No newline at end of file (base)"),
+        (false, true) => Some("\\ No newline at end of file (head)"),
+        (false, false) => None,
+    }
+}
+
+/// Base lines on the left, head lines on the right, each numbered.
+fn side_by_side_rows(change: &FileChange, moved: &MovedLines, args: &Args) -> Vec<Vec<Cell>> {
+    let numbered_cell = |(num, origin, line): &LineChange, moved: &HashSet<usize>, side: Side| {
+        line_cell(
+            format!(
+                "{} {}",
+                shown_number(*num, change, side, args),
+                display_line(*origin, line, args)
+            ),
+            *origin,
+            line,
+            moved.contains(num),
+        )
+    };
+    let mut base_cells: Vec<Cell> = change
+        .base_lines
+        .iter()
+        .map(|line| numbered_cell(line, &moved.base, Side::Base))
+        .collect();
+    let mut head_cells: Vec<Cell> = change
+        .head_lines
+        .iter()
+        .map(|line| numbered_cell(line, &moved.head, Side::Head))
+        .collect();
+
+    let max_len = base_cells.len().max(head_cells.len());
+    base_cells.resize(max_len, Cell::new(""));
+    head_cells.resize(max_len, Cell::new(""));
+
+    base_cells
+        .into_iter()
+        .zip(head_cells)
+        .map(|(base, head)| vec![Cell::new(""), base, head])
+        .collect()
+}
+
+/// A single column of lines with separate old and new line number gutters.
+fn unified_rows(change: &FileChange, moved: &MovedLines, args: &Args) -> Vec<Vec<Cell>> {
+    let gutter = |num: Option<usize>, side: Side| {
+        let num = num.map(|num| shown_number(num, change, side, args).to_string());
+        Cell::new(num.unwrap_or_default())
+            .fg(color::adapt(Color::DarkGrey))
+            .set_alignment(CellAlignment::Right)
+    };
+
+    let is_moved = |base_num: Option<usize>, head_num: Option<usize>, origin: Origin| {
+        if origin == Origin::Removed {
+            base_num.is_some_and(|num| moved.base.contains(&num))
+        } else {
+            head_num.is_some_and(|num| moved.head.contains(&num))
+        }
+    };
+
+    unified_lines(&change.base_lines, &change.head_lines)
+        .into_iter()
+        .map(|(base_num, head_num, origin, line)| {
+            vec![
+                Cell::new(""),
+                gutter(base_num, Side::Base),
+                gutter(head_num, Side::Head),
+                line_cell(
+                    display_line(origin, line, args),
+                    origin,
+                    line,
+                    is_moved(base_num, head_num, origin),
+                ),
+            ]
+        })
+        .collect()
+}
+
+/// One column of lines, each prefixed with its head line number (or base line
+/// number, for removed lines).
+fn inline_rows(change: &FileChange, moved: &MovedLines, args: &Args) -> Vec<Vec<Cell>> {
+    unified_lines(&change.base_lines, &change.head_lines)
+        .into_iter()
+        .map(|(base_num, head_num, origin, line)| {
+            let (num, side, is_moved) = if origin == Origin::Removed {
+                (
+                    base_num,
+                    Side::Base,
+                    base_num.is_some_and(|num| moved.base.contains(&num)),
+                )
+            } else {
+                (
+                    head_num,
+                    Side::Head,
+                    head_num.is_some_and(|num| moved.head.contains(&num)),
+                )
+            };
+            let num = num
+                .map(|num| shown_number(num, change, side, args).to_string())
+                .unwrap_or_default();
+            vec![line_cell(
+                format!("{} {}", num, display_line(origin, line, args)),
+                origin,
+                line,
+                is_moved,
+            )]
+        })
+        .collect()
+}
+
+/// Rows for each hunk in turn, each under a separator naming where it starts
+/// and which hunk of the file it is, e.g. `── src/main.rs:42 ── hunk 2/5 ──`.
+/// Hunks are laid out separately, so side-by-side columns realign at each one.
+fn decorated_rows(
+    file: &str,
+    change: &FileChange,
+    args: &Args,
+    view_rows: impl Fn(&FileChange) -> Vec<Vec<Cell>>,
+) -> Vec<Vec<Cell>> {
+    let hunks = split_hunks(change);
+    let mut rows = Vec::new();
+    for (idx, (&(base_start, head_start), hunk)) in change.hunks.iter().zip(&hunks).enumerate() {
+        if hunk.base_lines.is_empty() && hunk.head_lines.is_empty() {
+            continue;
+        }
+        let line = if head_start > 0 {
+            head_start
+        } else {
+            base_start
+        };
+        let separator = format!(
+            "── {}:{} ── hunk {}/{} ──",
+            file,
+            line,
+            idx + 1,
+            hunks.len()
+        );
+        rows.push(note_row(
+            Cell::new(separator).fg(color::adapt(Color::Blue)),
+            args.view,
+            false,
+        ));
+        rows.extend(view_rows(hunk));
+    }
+    rows
+}
+
+/// One copy of `change` per hunk, holding just that hunk's lines.
+fn split_hunks(change: &FileChange) -> Vec<FileChange> {
+    let hunk_of = |num: usize, side: Side| change.hunk_of(num, side).unwrap_or(0);
+    (0..change.hunks.len())
+        .map(|idx| FileChange {
+            base_lines: change
+                .base_lines
+                .iter()
+                .filter(|(num, _, _)| hunk_of(*num, Side::Base) == idx)
+                .cloned()
+                .collect(),
+            head_lines: change
+                .head_lines
+                .iter()
+                .filter(|(num, _, _)| hunk_of(*num, Side::Head) == idx)
+                .cloned()
+                .collect(),
+            ..change.clone()
+        })
+        .collect()
+}
+
+/// The number shown for line `num` of one side: as is, or counted from 1 at
+/// the start of its hunk with `--hunk-line-numbers`.
+fn shown_number(num: usize, change: &FileChange, side: Side, args: &Args) -> usize {
+    if !args.hunk_line_numbers {
+        return num;
+    }
+    let start = change
+        .hunks
+        .iter()
+        .map(|&(base, head)| match side {
+            Side::Base => base,
+            Side::Head => head,
+        })
+        .filter(|&start| start <= num)
+        .max()
+        .unwrap_or(1);
+    num - start + 1
+}
+
+/// Drops context lines further than `context` lines from any change.
+pub fn only_changes(change: &FileChange, context: usize) -> FileChange {
+    let lines = unified_lines(&change.base_lines, &change.head_lines);
+    let mut near_change = vec![false; lines.len()];
+    for (idx, (_, _, origin, _)) in lines.iter().enumerate() {
+        if *origin != Origin::Context {
+            let end = (idx + context).min(lines.len() - 1);
+            near_change[idx.saturating_sub(context)..=end].fill(true);
+        }
+    }
 
-        let base_cells: Vec<Cell> = base_lines
+    let mut keep_base = HashSet::new();
+    let mut keep_head = HashSet::new();
+    for ((base_num, head_num, _, _), keep) in lines.iter().zip(near_change) {
+        if keep {
+            keep_base.extend(*base_num);
+            keep_head.extend(*head_num);
+        }
+    }
+
+    FileChange {
+        base_lines: change
+            .base_lines
             .iter()
-            .map(|(num, line)| {
-                let mut cell = Cell::new(format!("{} {}", num, line));
-                if line.starts_with('-') {
-                    cell = cell.fg(Color::Red);
-                } else if line.starts_with('+') {
-                    cell = cell.fg(Color::Green);
-                }
-                cell
-            })
-            .collect();
-
-        let head_cells: Vec<Cell> = head_lines
+            .filter(|(num, _, _)| keep_base.contains(num))
+            .cloned()
+            .collect(),
+        head_lines: change
+            .head_lines
             .iter()
-            .map(|(num, line)| {
-                let mut cell = Cell::new(format!("{} {}", num, line));
-                if line.starts_with('-') {
-                    cell = cell.fg(Color::Red);
-                } else if line.starts_with('+') {
-                    cell = cell.fg(Color::Green);
-                }
-                cell
-            })
-            .collect();
-
-        // Add a single row with the combined lines
-        let max_len = max_lines;
-        let mut base_cells_padded = base_cells.clone();
-        let mut head_cells_padded = head_cells.clone();
-
-        base_cells_padded.resize(max_len, Cell::new(""));
-        head_cells_padded.resize(max_len, Cell::new(""));
+            .filter(|(num, _, _)| keep_head.contains(num))
+            .cloned()
+            .collect(),
+        ..change.clone()
+    }
+}
 
-        // Add rows to the table
-        for i in 0..max_len {
-            table.add_row(vec![
-                Cell::new(""), // Placeholder for the first column
-                base_cells_padded[i].clone(),
-                head_cells_padded[i].clone(),
-            ]);
+/// Drops the removed lines (for [`LineFilter::Added`]) or the added lines
+/// (for [`LineFilter::Removed`]).
+fn keep_changes(change: &FileChange, filter: LineFilter) -> FileChange {
+    let (base_lines, head_lines) = match filter {
+        LineFilter::Added => (
+            change
+                .base_lines
+                .iter()
+                .filter(|(_, origin, _)| *origin != Origin::Removed)
+                .cloned()
+                .collect(),
+            change.head_lines.clone(),
+        ),
+        LineFilter::Removed => (
+            change.base_lines.clone(),
+            change
+                .head_lines
+                .iter()
+                .filter(|(_, origin, _)| *origin != Origin::Added)
+                .cloned()
+                .collect(),
+        ),
+    };
+    FileChange {
+        base_lines,
+        head_lines,
+        ..change.clone()
+    }
+}
+
+/// Interleaves the two sides back into diff order, pairing each line with its
+/// base and head line numbers. Removed lines have no head number and added
+/// lines no base number.
+pub fn unified_lines<'a>(
+    base_lines: &'a [LineChange],
+    head_lines: &'a [LineChange],
+) -> Vec<(Option<usize>, Option<usize>, Origin, &'a str)> {
+    let mut base = base_lines.iter().peekable();
+    let mut head = head_lines.iter().peekable();
+    let mut lines = Vec::new();
+
+    loop {
+        if let Some((num, origin, line)) = base.next_if(|(_, origin, _)| *origin == Origin::Removed)
+        {
+            lines.push((Some(*num), None, *origin, line.as_str()));
+            continue;
+        }
+        if let Some((num, origin, line)) = head.next_if(|(_, origin, _)| *origin == Origin::Added) {
+            lines.push((None, Some(*num), *origin, line.as_str()));
+            continue;
+        }
+        match (base.next(), head.next()) {
+            (Some((base_num, origin, line)), Some((head_num, _, _))) => {
+                lines.push((Some(*base_num), Some(*head_num), *origin, line.as_str()))
+            }
+            (Some((num, origin, line)), None) => {
+                lines.push((Some(*num), None, *origin, line.as_str()))
+            }
+            (None, Some((num, origin, line))) => {
+                lines.push((None, Some(*num), *origin, line.as_str()))
+            }
+            (None, None) => break,
+        }
+    }
+
+    lines
+}
+
+/// A row with `cell` in the file column (`in_file_column`) or the first
+/// content column, padded to the view's width.
+pub fn note_row(cell: Cell, view: View, in_file_column: bool) -> Vec<Cell> {
+    let columns = match view {
+        View::SideBySide => 3,
+        View::Unified => 4,
+        View::Inline => 1,
+    };
+    let position = match (in_file_column, view) {
+        (true, _) | (false, View::Inline) => 0,
+        (false, View::SideBySide) => 1,
+        (false, View::Unified) => 3,
+    };
+
+    let mut row: Vec<Cell> = (0..columns).map(|_| Cell::new("")).collect();
+    row[position] = cell;
+    row
+}
+
+fn moved_lines(change: &FileChange, args: &Args) -> MovedLines {
+    if args.color_moved {
+        moved::detect_moved(change)
+    } else {
+        MovedLines::default()
+    }
+}
+
+/// A cell for a diff line, coloured by its origin, in the moved colour when
+/// it's part of a moved block, or by kind for conflict markers.
+fn line_cell(text: String, origin: Origin, line: &str, moved: bool) -> Cell {
+    let cell = Cell::new(text);
+    if let Some(marker) = conflict_marker(line) {
+        let color = match marker {
+            ConflictMarker::Ours => Color::Yellow,
+            ConflictMarker::Base | ConflictMarker::Separator => Color::Magenta,
+            ConflictMarker::Theirs => Color::Cyan,
+        };
+        cell.fg(color::adapt(color)).add_attribute(Attribute::Bold)
+    } else if moved {
+        cell.fg(color::adapt(MOVED_COLOR))
+    } else {
+        match origin {
+            Origin::Removed => cell.fg(color::adapt(Color::Red)),
+            Origin::Added => cell.fg(color::adapt(Color::Green)),
+            Origin::Context => cell,
         }
     }
 }
+
+/// One row per file with its status and added/removed line counts, numbered
+/// for `--file`. With `stat_bars`, each row ends with its share of a bar
+/// scaled to the largest change in the set, additions right-aligned against
+/// deletions.
+pub fn populate_summary_table(table: &mut Table, file_changes: &FileChanges, stat_bars: bool) {
+    let max_changes = file_changes
+        .values()
+        .map(|change| change.additions() + change.deletions())
+        .max()
+        .unwrap_or(0);
+
+    for (number, (file, change)) in file_changes.iter().enumerate() {
+        let (additions, deletions) = (change.additions(), change.deletions());
+        let mut row = vec![
+            Cell::new(number + 1)
+                .fg(color::adapt(Color::DarkGrey))
+                .set_alignment(CellAlignment::Right),
+            Cell::new(change.display_path(file)),
+            Cell::new(change.status.letter()).set_alignment(CellAlignment::Center),
+            Cell::new(format!("+{}", additions))
+                .fg(color::adapt(Color::Green))
+                .set_alignment(CellAlignment::Right),
+            Cell::new(format!("-{}", deletions))
+                .fg(color::adapt(Color::Red))
+                .set_alignment(CellAlignment::Right),
+        ];
+        if stat_bars {
+            let (plus, minus) =
+                stat::bar_lengths(additions, deletions, max_changes, SUMMARY_BAR_WIDTH);
+            row.push(
+                Cell::new("+".repeat(plus))
+                    .fg(color::adapt(Color::Green))
+                    .set_alignment(CellAlignment::Right),
+            );
+            row.push(Cell::new("-".repeat(minus)).fg(color::adapt(Color::Red)));
+        }
+        table.add_row(row);
+    }
+}
+
+/// Explains a file listed in the diff without any lines, e.g. one that was
+/// only renamed or had its mode changed.
+fn no_content_note(change: &FileChange) -> String {
+    match (change.old_mode.as_deref(), change.new_mode.as_deref()) {
+        (Some(old), Some(new)) if old != new => {
+            format!("(no content changes; mode {} → {})", old, new)
+        }
+        _ => match change.status {
+            FileStatus::Added => "(new empty file)".to_string(),
+            FileStatus::Deleted => "(deleted empty file)".to_string(),
+            _ => "(no content changes)".to_string(),
+        },
+    }
+}
+
+fn short_commit(commit: Option<&str>, len: usize) -> &str {
+    match commit {
+        Some(commit) => giff::abbreviate(commit, len),
+        None => "(none)",
+    }
+}
+
+/// The text shown for a line: its marker, then its content as the options
+/// ask for it.
+fn display_line(origin: Origin, line: &str, args: &Args) -> String {
+    let truncated;
+    let line = match truncate_long_line(line) {
+        Some(short) => {
+            truncated = short;
+            truncated.as_str()
+        }
+        None => line,
+    };
+    let mut shown = origin.marker().to_string();
+    if args.show_whitespace {
+        shown.push_str(&visualize_whitespace(line));
+    } else {
+        shown.push_str(line);
+    }
+    let line = shown;
+    match args.wrap_at {
+        Some(cols) => hard_wrap(&line, cols),
+        None => line,
+    }
+}
+
+/// Cuts a line over [`LONG_LINE_CHARS`] down to a preview and says how long it
+/// was, so one enormous line doesn't swamp the table. Returns `None` for lines
+/// short enough to show in full.
+fn truncate_long_line(line: &str) -> Option<String> {
+    let (cut, _) = line.char_indices().nth(LONG_LINE_CHARS)?;
+    let chars = LONG_LINE_CHARS + line[cut..].chars().count();
+    let (preview, _) = line
+        .char_indices()
+        .nth(LONG_LINE_PREVIEW)
+        .unwrap_or((cut, ' '));
+    Some(format!(
+        "{} … [line too long: {} chars]",
+        &line[..preview],
+        chars
+    ))
+}
+
+/// Breaks `line` into pieces of at most `cols` characters, starting each
+/// continuation with `↪` so it can't be mistaken for a line of its own.
+pub fn hard_wrap(line: &str, cols: usize) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    if cols < 2 || chars.len() <= cols {
+        return line.to_string();
+    }
+
+    let (first, rest) = chars.split_at(cols);
+    let mut wrapped: String = first.iter().collect();
+    for piece in rest.chunks(cols - 1) {
+        wrapped.push_str("\n↪");
+        wrapped.extend(piece);
+    }
+    wrapped
+}
+
+/// Explains the colours and symbols the diff table uses, one row each.
+pub fn populate_legend(table: &mut Table) {
+    let entries = [
+        (
+            Cell::new("+line").fg(color::adapt(Color::Green)),
+            "added line",
+        ),
+        (
+            Cell::new("-line").fg(color::adapt(Color::Red)),
+            "removed line",
+        ),
+        (
+            Cell::new("old → new").fg(color::adapt(Color::Yellow)),
+            "renamed file",
+        ),
+        (
+            Cell::new("±line").fg(color::adapt(MOVED_COLOR)),
+            "moved line (--color-moved)",
+        ),
+        (
+            Cell::new("<<<<<<<").fg(color::adapt(Color::Yellow)),
+            "conflict markers: ours, base, theirs",
+        ),
+        (
+            Cell::new("submodule, binary").fg(color::adapt(Color::Cyan)),
+            "summary of a change with no lines",
+        ),
+        (
+            Cell::new("(note)").fg(color::adapt(Color::DarkGrey)),
+            "truncation, line endings and files without content changes",
+        ),
+        (
+            Cell::new("── file:line ──").fg(color::adapt(Color::Blue)),
+            "start of a hunk (--decorations)",
+        ),
+        (Cell::new("↪"), "continues a wrapped line (--wrap-at)"),
+        (
+            Cell::new("→ · ¬"),
+            "tab, trailing space, end of line (--show-whitespace)",
+        ),
+        (
+            Cell::new("↑2 ↓3 ✚dirty"),
+            "commits ahead of and behind upstream, uncommitted changes",
+        ),
+    ];
+    for (key, meaning) in entries {
+        table.add_row(vec![key, Cell::new(meaning)]);
+    }
+}
+
+/// Makes whitespace visible in a line's content: tabs become `→` padded to four
+/// columns, trailing spaces become `·`, and `¬` marks the end of the line.
+pub fn visualize_whitespace(content: &str) -> String {
+    let body = content.trim_end_matches(' ');
+    let trailing = content.len() - body.len();
+
+    let mut visible = body.replace('\t', "→   ");
+    visible.push_str(&"·".repeat(trailing));
+    visible.push('¬');
+    visible
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_diff_output;
+    use clap::Parser;
+
+    /// Two hunks of `f`: a change in the first and an addition in the second.
+    const TWO_HUNKS: &str = "\
+diff --git a/f b/f
+--- a/f
++++ b/f
+@@ -1,5 +1,5 @@
+\x201
+\x202
+-3
++three
+\x204
+\x205
+@@ -20,3 +20,4 @@
+\x2020
++20.5
+\x2021
+\x2022
+";
+
+    fn two_hunks() -> FileChange {
+        parse_diff_output(TWO_HUNKS).remove("f").unwrap()
+    }
+
+    #[test]
+    fn unified_lines_number_each_side() {
+        let diff = "\
+diff --git a/f b/f
+--- a/f
++++ b/f
+@@ -3,4 +3,4 @@
+\x20keep
+-old
++new
++extra
+\x20tail
+-dropped
+";
+        let change = parse_diff_output(diff).remove("f").unwrap();
+        assert_eq!(
+            unified_lines(&change.base_lines, &change.head_lines),
+            [
+                (Some(3), Some(3), Origin::Context, "keep"),
+                (Some(4), None, Origin::Removed, "old"),
+                (None, Some(4), Origin::Added, "new"),
+                (None, Some(5), Origin::Added, "extra"),
+                (Some(5), Some(6), Origin::Context, "tail"),
+                (Some(6), None, Origin::Removed, "dropped"),
+            ]
+        );
+    }
+
+    #[test]
+    fn short_lines_are_not_wrapped() {
+        assert_eq!(hard_wrap("abcd", 4), "abcd");
+        assert_eq!(hard_wrap("abcdef", 1), "abcdef");
+    }
+
+    #[test]
+    fn continuations_are_marked_and_fit_the_width() {
+        assert_eq!(hard_wrap("abcdefghij", 4), "abcd\n↪efg\n↪hij");
+        assert_eq!(hard_wrap("äöüßéñ", 3), "äöü\n↪ßé\n↪ñ");
+    }
+
+    #[test]
+    fn tabs_and_trailing_spaces_are_shown() {
+        assert_eq!(visualize_whitespace("\tx = 1;  "), "→   x = 1;··¬");
+        assert_eq!(visualize_whitespace("a b"), "a b¬");
+        assert_eq!(visualize_whitespace(""), "¬");
+    }
+
+    fn numbers(lines: &[LineChange]) -> Vec<usize> {
+        lines.iter().map(|(num, _, _)| *num).collect()
+    }
+
+    #[test]
+    fn only_changes_keeps_context_next_to_changes() {
+        let change = only_changes(&two_hunks(), 1);
+        assert_eq!(numbers(&change.base_lines), [2, 3, 4, 20, 21]);
+        assert_eq!(numbers(&change.head_lines), [2, 3, 4, 20, 21, 22]);
+        assert_eq!(numbers(&only_changes(&two_hunks(), 0).base_lines), [3]);
+    }
+
+    #[test]
+    fn keep_changes_drops_the_other_side() {
+        let added = keep_changes(&two_hunks(), LineFilter::Added);
+        assert_eq!(numbers(&added.base_lines), [1, 2, 4, 5, 20, 21, 22]);
+        assert_eq!(added.head_lines, two_hunks().head_lines);
+
+        let removed = keep_changes(&two_hunks(), LineFilter::Removed);
+        assert_eq!(removed.base_lines, two_hunks().base_lines);
+        assert_eq!(numbers(&removed.head_lines), [1, 2, 4, 5, 20, 22, 23]);
+    }
+
+    #[test]
+    fn hunk_line_numbers_count_from_each_hunk() {
+        let change = two_hunks();
+        let plain = Args::try_parse_from(["giff"]).unwrap();
+        let relative = Args::try_parse_from(["giff", "--hunk-line-numbers"]).unwrap();
+
+        assert_eq!(shown_number(21, &change, Side::Head, &plain), 21);
+        assert_eq!(shown_number(1, &change, Side::Base, &relative), 1);
+        assert_eq!(shown_number(4, &change, Side::Base, &relative), 4);
+        assert_eq!(shown_number(20, &change, Side::Base, &relative), 1);
+        assert_eq!(shown_number(23, &change, Side::Head, &relative), 4);
+    }
+
+    #[test]
+    fn split_hunks_keeps_each_hunk_s_lines() {
+        let hunks = split_hunks(&two_hunks());
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(numbers(&hunks[0].base_lines), [1, 2, 3, 4, 5]);
+        assert_eq!(numbers(&hunks[1].head_lines), [20, 21, 22, 23]);
+    }
+
+    #[test]
+    fn each_hunk_is_decorated_with_a_separator() {
+        let args = Args::try_parse_from(["giff", "--decorations", "--view", "inline"]).unwrap();
+        let rows = decorated_rows("f", &two_hunks(), &args, |hunk| {
+            vec![vec![Cell::new(hunk.head_lines.len())]]
+        });
+        let text: Vec<String> = rows.iter().map(|row| row[0].content()).collect();
+        assert_eq!(
+            text,
+            ["── f:1 ── hunk 1/2 ──", "5", "── f:20 ── hunk 2/2 ──", "4"]
+        );
+    }
+
+    #[test]
+    fn files_without_content_changes_get_a_note() {
+        let diff = "diff --git a/run.sh b/run.sh\n\
+                    old mode 100644\n\
+                    new mode 100755\n\
+                    diff --git a/empty b/empty\n\
+                    new file mode 100644\n\
+                    index 0000000..e69de29\n\
+                    diff --git a/gone b/gone\n\
+                    deleted file mode 100644\n\
+                    index e69de29..0000000\n";
+        let changes = parse_diff_output(diff);
+        assert_eq!(
+            changes.keys().collect::<Vec<_>>(),
+            ["empty", "gone", "run.sh"]
+        );
+        assert_eq!(
+            no_content_note(&changes["run.sh"]),
+            "(no content changes; mode 100644 → 100755)"
+        );
+        assert_eq!(no_content_note(&changes["empty"]), "(new empty file)");
+        assert_eq!(no_content_note(&changes["gone"]), "(deleted empty file)");
+    }
+}
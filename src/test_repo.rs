@@ -0,0 +1,72 @@
+//! A small repository for the tests that run git, made once per test run.
+//!
+//! Its history on `main`:
+//!
+//! - `root`: adds `notes.txt`.
+//! - `renamed`: renames it to `my notes.txt` and adds a line.
+//! - `HEAD`: adds `src/lib.rs` and edits `my notes.txt` again.
+
+use crate::giff;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// Creates the repository on first use and points every [`giff::git`]
+/// command at it.
+pub fn init() -> &'static Path {
+    static REPO: OnceLock<PathBuf> = OnceLock::new();
+    REPO.get_or_init(|| {
+        let dir = std::env::temp_dir().join(format!("giff-test-repo-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        git(&dir, &["init", "-q", "-b", "main"]);
+        fs::write(dir.join("notes.txt"), "one\ntwo\n").unwrap();
+        commit(&dir, "Add notes");
+        git(&dir, &["tag", "root"]);
+
+        git(&dir, &["mv", "notes.txt", "my notes.txt"]);
+        fs::write(dir.join("my notes.txt"), "one\ntwo\nthree\n").unwrap();
+        commit(&dir, "Rename notes");
+        git(&dir, &["tag", "renamed"]);
+
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(
+            dir.join("src/lib.rs"),
+            "pub fn helper() -> u32 {\n    42\n}\n",
+        )
+        .unwrap();
+        fs::write(dir.join("my notes.txt"), "one\n2\nthree\nfour\n").unwrap();
+        commit(&dir, "Add a helper");
+
+        giff::set_work_dir(dir.clone());
+        dir
+    })
+}
+
+fn commit(dir: &Path, message: &str) {
+    git(dir, &["add", "-A"]);
+    git(dir, &["commit", "-q", "-m", message]);
+}
+
+/// Runs git in `dir`, independent of the user's config.
+pub fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(["-c", "commit.gpgSign=false"])
+        .args(args)
+        .env("GIT_CONFIG_GLOBAL", "/dev/null")
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "git {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
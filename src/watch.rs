@@ -0,0 +1,56 @@
+use crate::args::Args;
+use crate::giff::{self, DiffOutput};
+use crossterm::terminal;
+use std::error::Error;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Re-runs the diff on an interval and calls `redraw` whenever it changes.
+///
+/// A change is only redrawn once the output is the same across two polls, so a
+/// burst of writes (e.g. a rebase or checkout in progress) results in a single
+/// redraw. The table is also redrawn when the terminal is resized, since its
+/// column widths depend on the terminal width. Runs until the process is
+/// interrupted, or until nothing has changed for `--idle-timeout` seconds.
+pub fn watch<F>(
+    args: &Args,
+    mut last_output: DiffOutput,
+    mut redraw: F,
+) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(&DiffOutput) -> Result<(), Box<dyn Error>>,
+{
+    let mut pending: Option<DiffOutput> = None;
+    let mut size = terminal::size().ok();
+    let idle_timeout = args.idle_timeout.map(Duration::from_secs);
+    let mut last_change = Instant::now();
+
+    loop {
+        if idle_timeout.is_some_and(|timeout| last_change.elapsed() >= timeout) {
+            return Ok(());
+        }
+        thread::sleep(POLL_INTERVAL);
+
+        let new_size = terminal::size().ok();
+        if new_size != size {
+            size = new_size;
+            redraw(&last_output)?;
+        }
+
+        let output = giff::get_diff_output(args)?;
+
+        if output == last_output {
+            pending = None;
+        } else if pending.as_ref() == Some(&output) {
+            redraw(&output)?;
+            last_output = output;
+            pending = None;
+            last_change = Instant::now();
+        } else {
+            pending = Some(output);
+            last_change = Instant::now();
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use crate::giff;
+use crate::parser::{FileChanges, Origin};
+
+/// The whitespace problems to look for, from git's `core.whitespace`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rules {
+    pub blank_at_eol: bool,
+    pub space_before_tab: bool,
+    pub tab_in_indent: bool,
+}
+
+impl Default for Rules {
+    /// Git's defaults: `blank-at-eol` and `space-before-tab` are on.
+    fn default() -> Self {
+        Rules {
+            blank_at_eol: true,
+            space_before_tab: true,
+            tab_in_indent: false,
+        }
+    }
+}
+
+impl Rules {
+    /// The rules configured in `core.whitespace`, where `-name` turns one off.
+    pub fn from_git_config() -> Rules {
+        let mut rules = Rules::default();
+        let config = giff::git_output(&["config", "--get", "core.whitespace"]).unwrap_or_default();
+        for rule in config.split(',').map(str::trim) {
+            let (name, enabled) = match rule.strip_prefix('-') {
+                Some(name) => (name, false),
+                None => (rule, true),
+            };
+            match name {
+                "blank-at-eol" | "trailing-space" => rules.blank_at_eol = enabled,
+                "space-before-tab" => rules.space_before_tab = enabled,
+                "tab-in-indent" => rules.tab_in_indent = enabled,
+                _ => {}
+            }
+        }
+        rules
+    }
+}
+
+/// An added line with a whitespace problem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WhitespaceError {
+    pub file: String,
+    pub line_number: usize,
+    pub problem: &'static str,
+    /// The added line's content, without its `+`.
+    pub line: String,
+}
+
+/// Finds whitespace problems in the lines the diff adds.
+pub fn check(file_changes: &FileChanges, rules: Rules) -> Vec<WhitespaceError> {
+    let mut errors = Vec::new();
+    for (file, change) in file_changes {
+        for (line_number, origin, content) in &change.head_lines {
+            if *origin != Origin::Added {
+                continue;
+            }
+
+            let indent_len = content.len() - content.trim_start_matches([' ', '\t']).len();
+            let indent = &content[..indent_len];
+            let problems = [
+                (
+                    rules.blank_at_eol && content.ends_with([' ', '\t']),
+                    "trailing whitespace",
+                ),
+                (
+                    rules.space_before_tab && indent.contains(" \t"),
+                    "space before tab in indent",
+                ),
+                (
+                    rules.tab_in_indent && indent.contains('\t'),
+                    "indent with tab",
+                ),
+            ];
+            for (found, problem) in problems {
+                if found {
+                    errors.push(WhitespaceError {
+                        file: file.clone(),
+                        line_number: *line_number,
+                        problem,
+                        line: content.clone(),
+                    });
+                }
+            }
+        }
+
+        // Only the last line can lack a final newline.
+        let last = change.head_lines.last();
+        if let Some((line_number, Origin::Added, content)) = last.filter(|_| change.no_newline.1) {
+            errors.push(WhitespaceError {
+                file: file.clone(),
+                line_number: *line_number,
+                problem: "no newline at end of file",
+                line: content.clone(),
+            });
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_diff_output;
+
+    #[test]
+    fn missing_final_newline_is_reported_on_the_added_line() {
+        let diff = "diff --git a/f b/f\n\
+                    --- a/f\n\
+                    +++ b/f\n\
+                    @@ -1 +1,2 @@\n\
+                    \x20a\n\
+                    +b \n\
+                    \\ No newline at end of file\n";
+        let errors = check(&parse_diff_output(diff), Rules::default());
+        let problems: Vec<_> = errors
+            .iter()
+            .map(|error| (error.line_number, error.problem))
+            .collect();
+        assert_eq!(
+            problems,
+            [(2, "trailing whitespace"), (2, "no newline at end of file")]
+        );
+    }
+
+    #[test]
+    fn missing_newline_on_the_removed_side_is_not_reported() {
+        let diff = "diff --git a/f b/f\n\
+                    --- a/f\n\
+                    +++ b/f\n\
+                    @@ -1 +1 @@\n\
+                    -a\n\
+                    \\ No newline at end of file\n\
+                    +a\n";
+        assert!(check(&parse_diff_output(diff), Rules::default()).is_empty());
+    }
+}